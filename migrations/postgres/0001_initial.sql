@@ -1,7 +1,5 @@
-DROP TABLE IF EXISTS config;
-DROP TABLE IF EXISTS days;
-
-CREATE TABLE config (
+-- the schema before migrations were introduced, existing databases already have these tables
+CREATE TABLE IF NOT EXISTS config (
     id uuid PRIMARY KEY NOT NULL,
     cost_heating double precision NOT NULL,
     cost_general double precision NOT NULL,
@@ -9,7 +7,7 @@ CREATE TABLE config (
     monthly_budget_general double precision NOT NULL
 );
 
-CREATE TABLE days (
+CREATE TABLE IF NOT EXISTS days (
     id uuid PRIMARY KEY NOT NULL,
     heating_consumption double precision NOT NULL,
     general_consumption double precision NOT NULL,
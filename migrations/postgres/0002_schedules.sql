@@ -0,0 +1,4 @@
+CREATE TABLE schedules (
+    name text PRIMARY KEY NOT NULL,
+    cron text NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,7 @@
+-- every row is a version of the config, valid until the next version
+-- the existing config has been valid all along
+ALTER TABLE config ADD COLUMN valid_from date;
+UPDATE config SET valid_from = '1970-01-01';
+ALTER TABLE config ALTER COLUMN valid_from SET NOT NULL;
+ALTER TABLE config DROP COLUMN id;
+ALTER TABLE config ADD PRIMARY KEY (valid_from);
\ No newline at end of file
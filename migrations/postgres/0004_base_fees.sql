@@ -0,0 +1,2 @@
+ALTER TABLE config ADD COLUMN base_fee_heating double precision NOT NULL DEFAULT 0;
+ALTER TABLE config ADD COLUMN base_fee_general double precision NOT NULL DEFAULT 0;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- fresh databases start with a default config, which can be changed with /config
+INSERT INTO config (valid_from, cost_heating, cost_general, monthly_budget_heating, monthly_budget_general, base_fee_heating, base_fee_general)
+SELECT '1970-01-01', 0.23, 0.3, 460, 50, 0, 0
+WHERE NOT EXISTS (SELECT 1 FROM config);
\ No newline at end of file
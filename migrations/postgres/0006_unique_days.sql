@@ -0,0 +1,7 @@
+-- every row contains the consumption of all meters for a day, so the date identifies it
+-- ids are random and say nothing about the order, so of days that were saved more than once
+-- keep the row with the most consumption, which is the most complete fetch, using the id to break ties
+DELETE FROM days a USING days b
+WHERE a.date = b.date
+    AND (a.heating_consumption + a.general_consumption, a.id) < (b.heating_consumption + b.general_consumption, b.id);
+ALTER TABLE days ADD CONSTRAINT days_date_key UNIQUE (date);
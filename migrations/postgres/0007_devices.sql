@@ -0,0 +1,6 @@
+-- the devices linked to the Powerfox-account, updated whenever reports are fetched
+CREATE TABLE devices (
+    device_id text PRIMARY KEY NOT NULL,
+    name text NOT NULL,
+    main_device boolean NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- the consumption of all meters per hour, saved together with the day they belong to
+CREATE TABLE hours (
+    timestamp timestamptz PRIMARY KEY NOT NULL,
+    date date NOT NULL,
+    heating_consumption double precision NOT NULL,
+    general_consumption double precision NOT NULL
+);
+
+CREATE INDEX hours_date_idx ON hours (date);
\ No newline at end of file
@@ -0,0 +1,14 @@
+-- every run of a job, whether it was scheduled or started manually
+CREATE TABLE job_runs (
+    id bigserial PRIMARY KEY,
+    job text NOT NULL,
+    triggered_by text NOT NULL,
+    started_at timestamptz NOT NULL,
+    finished_at timestamptz,
+    status text NOT NULL,
+    error text,
+    -- the comma-separated days that were written
+    days text NOT NULL DEFAULT ''
+);
+
+CREATE INDEX job_runs_started_at_idx ON job_runs (started_at);
\ No newline at end of file
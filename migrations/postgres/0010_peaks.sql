@@ -0,0 +1,6 @@
+-- the highest report-value of each meter per day, which Powerfox reports as max
+CREATE TABLE peaks (
+    date date PRIMARY KEY NOT NULL,
+    heating_max double precision NOT NULL,
+    general_max double precision NOT NULL
+);
@@ -0,0 +1,36 @@
+-- SQLite starts without the history of the PostgreSQL-schema, so this creates the current schema at once
+
+-- every row is a version of the config, valid until the next version
+CREATE TABLE config (
+    valid_from TEXT PRIMARY KEY NOT NULL,
+    cost_heating REAL NOT NULL,
+    cost_general REAL NOT NULL,
+    monthly_budget_heating REAL NOT NULL,
+    monthly_budget_general REAL NOT NULL,
+    base_fee_heating REAL NOT NULL DEFAULT 0,
+    base_fee_general REAL NOT NULL DEFAULT 0
+);
+
+-- every row contains the consumption of all meters for a day, so the date identifies it
+CREATE TABLE days (
+    date TEXT PRIMARY KEY NOT NULL,
+    heating_consumption REAL NOT NULL,
+    general_consumption REAL NOT NULL,
+    average_temperature REAL NOT NULL
+);
+
+CREATE TABLE schedules (
+    name TEXT PRIMARY KEY NOT NULL,
+    cron TEXT NOT NULL
+);
+
+-- the devices linked to the Powerfox-account, updated whenever reports are fetched
+CREATE TABLE devices (
+    device_id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    main_device BOOLEAN NOT NULL
+);
+
+-- fresh databases start with a default config, which can be changed with /config
+INSERT INTO config (valid_from, cost_heating, cost_general, monthly_budget_heating, monthly_budget_general, base_fee_heating, base_fee_general)
+VALUES ('1970-01-01', 0.23, 0.3, 460, 50, 0, 0);
\ No newline at end of file
@@ -0,0 +1,14 @@
+-- every run of a job, whether it was scheduled or started manually
+CREATE TABLE job_runs (
+    id INTEGER PRIMARY KEY,
+    job TEXT NOT NULL,
+    triggered_by TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    finished_at TEXT,
+    status TEXT NOT NULL,
+    error TEXT,
+    -- the comma-separated days that were written
+    days TEXT NOT NULL DEFAULT ''
+);
+
+CREATE INDEX job_runs_started_at_idx ON job_runs (started_at);
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- the highest report-value of each meter per day, which Powerfox reports as max
+CREATE TABLE peaks (
+    date TEXT PRIMARY KEY NOT NULL,
+    heating_max REAL NOT NULL,
+    general_max REAL NOT NULL
+);
@@ -0,0 +1,275 @@
+use crate::db::{Config, Day, Days};
+use anyhow::Result;
+use serenity::utils::MessageBuilder;
+use std::env;
+
+/// Used if `ANOMALY_THRESHOLD` isn't set: how many standard deviations a day may deviate from the baseline.
+const DEFAULT_THRESHOLD: f64 = 3.0;
+
+/// The number of days before the checked day that the baseline is built from.
+pub const HISTORY_DAYS: i64 = 365;
+
+/// Fewer days don't make for a meaningful baseline, so nothing is flagged until there are enough.
+const MIN_DAYS: usize = 14;
+
+/// A meter's name, how to get its consumption from a day and the cost per kWh.
+type Meter = (&'static str, fn(&Day) -> f64, f64);
+
+/// A day whose consumption of a meter deviates from the baseline, see [detect].
+pub struct Anomaly {
+    meter: &'static str,
+    consumption: f64,
+    expected: f64,
+    z_score: f64,
+    /// How much more the day cost than expected, which is negative if it cost less.
+    extra_cost: f64,
+}
+
+/// The expected consumption at a temperature, fitted to the history with least squares.
+struct Baseline {
+    intercept: f64,
+    slope: f64,
+    /// The standard deviation of the residuals, i.e. how much days usually deviate from the baseline.
+    deviation: f64,
+}
+
+impl Baseline {
+    /// Fits the baseline to the pairs of temperature and consumption.
+    fn fit(points: &[(f64, f64)]) -> Option<Self> {
+        if points.len() < MIN_DAYS {
+            return None;
+        }
+        let count = points.len() as f64;
+        let mean_x = points.iter().fold(0.0, |sum, (x, _)| sum + x) / count;
+        let mean_y = points.iter().fold(0.0, |sum, (_, y)| sum + y) / count;
+        let covariance = points
+            .iter()
+            .fold(0.0, |sum, (x, y)| sum + (x - mean_x) * (y - mean_y));
+        let variance = points
+            .iter()
+            .fold(0.0, |sum, (x, _)| sum + (x - mean_x).powi(2));
+        // without different temperatures, the baseline is just the average
+        let slope = if variance > 0.0 {
+            covariance / variance
+        } else {
+            0.0
+        };
+        let intercept = mean_y - slope * mean_x;
+
+        // two parameters were fitted
+        let squares = points.iter().fold(0.0, |sum, (x, y)| {
+            sum + (y - (intercept + slope * x)).powi(2)
+        });
+        let deviation = (squares / (count - 2.0)).sqrt();
+        Some(Baseline {
+            intercept,
+            slope,
+            deviation,
+        })
+    }
+
+    fn expected(&self, temperature: f64) -> f64 {
+        // consumption can't be negative, even if the line is below zero for very warm days
+        (self.intercept + self.slope * temperature).max(0.0)
+    }
+}
+
+/// Compares the day's consumption of both meters with a baseline that takes the temperature into account,
+/// which is built from the history. Days are flagged if the z-score of their residual exceeds `ANOMALY_THRESHOLD`.
+pub fn detect(day: &Day, history: &Days, config: &Config) -> Result<Vec<Anomaly>> {
+    let threshold = match env::var("ANOMALY_THRESHOLD") {
+        Ok(threshold) => threshold.parse()?,
+        Err(_) => DEFAULT_THRESHOLD,
+    };
+    let history: Vec<&Day> = history.0.iter().filter(|old| old.date < day.date).collect();
+
+    let meters: [Meter; 2] = [
+        (
+            "heating",
+            |day| day.heating_consumption,
+            config.cost_heating,
+        ),
+        (
+            "general",
+            |day| day.general_consumption,
+            config.cost_general,
+        ),
+    ];
+    let mut anomalies = Vec::new();
+    for (meter, consumption_of, cost) in meters {
+        let points: Vec<(f64, f64)> = history
+            .iter()
+            .map(|old| (old.average_temperature, consumption_of(old)))
+            .collect();
+        let Some(baseline) = Baseline::fit(&points) else {
+            continue;
+        };
+        // if every day had the same consumption, any deviation would be infinitely unusual
+        if baseline.deviation <= f64::EPSILON {
+            continue;
+        }
+
+        let consumption = consumption_of(day);
+        let expected = baseline.expected(day.average_temperature);
+        let z_score = (consumption - expected) / baseline.deviation;
+        if z_score.abs() > threshold {
+            anomalies.push(Anomaly {
+                meter,
+                consumption,
+                expected,
+                z_score,
+                extra_cost: (consumption - expected) * cost,
+            });
+        }
+    }
+    Ok(anomalies)
+}
+
+impl Anomaly {
+    pub fn summary(&self) -> String {
+        let (direction, difference) = if self.extra_cost >= 0.0 {
+            ("more", self.extra_cost)
+        } else {
+            ("less", -self.extra_cost)
+        };
+        MessageBuilder::new()
+            .push_quote("Unusual ")
+            .push(self.meter)
+            .push("-consumption: ")
+            .push_bold_safe(format!("{:.2} kWh", self.consumption))
+            .push(" instead of about ")
+            .push_bold_safe(format!("{:.2} kWh", self.expected))
+            .push(format!(
+                " at this temperature (z-score {:.1}), which cost ",
+                self.z_score
+            ))
+            .push_bold_safe(format!("{:.2} €", difference))
+            .push(format!(" {} than usual.", direction))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Storage;
+    use crate::memory::Memory;
+    use chrono::{Duration, NaiveDate};
+
+    fn day(offset: i64, temperature: f64, heating_consumption: f64) -> Day {
+        Day {
+            heating_consumption,
+            general_consumption: 5.0,
+            average_temperature: temperature,
+            date: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap() + Duration::days(offset),
+        }
+    }
+
+    /// Two days per temperature from 0 to 9 °C, whose heating is 1 kWh above and below `20 - temperature`.
+    fn history() -> Days {
+        Days(
+            (0..20)
+                .map(|i| {
+                    let temperature = (i / 2) as f64;
+                    let noise = if i % 2 == 0 { 1.0 } else { -1.0 };
+                    day(i, temperature, 20.0 - temperature + noise)
+                })
+                .collect(),
+        )
+    }
+
+    async fn config() -> Config {
+        Memory::new().get_config().await.unwrap()
+    }
+
+    #[test]
+    fn fit_follows_the_temperature() {
+        let points: Vec<(f64, f64)> = history()
+            .0
+            .iter()
+            .map(|day| (day.average_temperature, day.heating_consumption))
+            .collect();
+        let baseline = Baseline::fit(&points).unwrap();
+        assert!((baseline.slope + 1.0).abs() < 1e-9);
+        assert!((baseline.intercept - 20.0).abs() < 1e-9);
+        assert!((baseline.deviation - (20.0f64 / 18.0).sqrt()).abs() < 1e-9);
+        assert_eq!(baseline.expected(30.0), 0.0);
+    }
+
+    #[test]
+    fn fit_of_collinear_points_has_no_deviation() {
+        let points: Vec<(f64, f64)> = (0..20).map(|i| (i as f64, 20.0 - i as f64)).collect();
+        let baseline = Baseline::fit(&points).unwrap();
+        assert!((baseline.slope + 1.0).abs() < 1e-9);
+        assert!(baseline.deviation < 1e-9);
+    }
+
+    #[test]
+    fn fit_without_different_temperatures_is_the_average() {
+        let points: Vec<(f64, f64)> = (0..20).map(|i| (10.0, (i % 2) as f64 * 2.0)).collect();
+        let baseline = Baseline::fit(&points).unwrap();
+        assert_eq!(baseline.slope, 0.0);
+        assert_eq!(baseline.intercept, 1.0);
+        assert!(baseline.deviation.is_finite());
+    }
+
+    #[test]
+    fn fit_needs_enough_points() {
+        let points: Vec<(f64, f64)> = (0..MIN_DAYS - 1).map(|i| (i as f64, i as f64)).collect();
+        assert!(Baseline::fit(&points).is_none());
+        assert!(Baseline::fit(&[]).is_none());
+    }
+
+    #[tokio::test]
+    async fn days_beyond_the_threshold_are_flagged() {
+        let config = config().await;
+        let deviation = (20.0f64 / 18.0).sqrt();
+        let check = |heating: f64| detect(&day(30, 5.0, heating), &history(), &config).unwrap();
+
+        assert!(check(15.0 + 2.9 * deviation).is_empty());
+        assert!(check(15.0 - 2.9 * deviation).is_empty());
+
+        let more = check(15.0 + 3.1 * deviation);
+        assert_eq!(more.len(), 1);
+        assert_eq!(more[0].meter, "heating");
+        assert!((more[0].z_score - 3.1).abs() < 1e-9);
+        assert!((more[0].extra_cost - 3.1 * deviation * config.cost_heating).abs() < 1e-9);
+
+        let less = check(15.0 - 3.1 * deviation);
+        assert_eq!(less.len(), 1);
+        assert!(less[0].extra_cost < 0.0);
+    }
+
+    #[tokio::test]
+    async fn nothing_is_flagged_without_history() {
+        let config = config().await;
+        let unusual = day(30, 5.0, 1000.0);
+        assert!(detect(&unusual, &Days(Vec::new()), &config)
+            .unwrap()
+            .is_empty());
+
+        // later days aren't part of the history
+        let later = Days(
+            history()
+                .0
+                .into_iter()
+                .map(|old| Day {
+                    date: old.date + Duration::days(60),
+                    ..old
+                })
+                .collect(),
+        );
+        assert!(detect(&unusual, &later, &config).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn constant_consumption_is_not_flagged() {
+        let config = config().await;
+        // the general consumption is always 5 kWh
+        let unusual = Day {
+            general_consumption: 50.0,
+            ..day(30, 5.0, 15.0)
+        };
+        assert!(detect(&unusual, &history(), &config).unwrap().is_empty());
+    }
+}
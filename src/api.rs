@@ -0,0 +1,329 @@
+use crate::{
+    db::{Aggregate, Config, Db, Device, Granularity, Trigger},
+    export::{self, ExportDay},
+    health::Health,
+    metrics,
+    scheduler::Scheduler,
+    util,
+};
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{Datelike, Duration, NaiveDate};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// Used if `HTTP_ADDRESS` isn't set, which only allows requests from the same host.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:3000";
+
+#[derive(Clone)]
+struct ApiState {
+    db: Db,
+    scheduler: Scheduler,
+    /// The bearer-token `POST /powerfox/daily` requires, which is disabled without one.
+    token: Option<String>,
+}
+
+/// Serves the HTTP-API at `HTTP_ADDRESS` until the app is stopped.
+pub async fn serve(db: Db, scheduler: Scheduler) -> Result<()> {
+    let address = env::var("HTTP_ADDRESS").unwrap_or(DEFAULT_ADDRESS.to_string());
+    let app = Router::new()
+        .route("/powerfox/days", get(days))
+        .route("/powerfox/stats", get(stats))
+        .route("/powerfox/month", get(month))
+        .route("/powerfox/year", get(year))
+        .route("/powerfox/config", get(config))
+        .route("/powerfox/config/history", get(config_history))
+        .route("/powerfox/devices", get(devices))
+        .route("/powerfox/daily", post(daily))
+        .route("/metrics", get(metrics))
+        .route("/health", get(health))
+        .with_state(ApiState {
+            db,
+            scheduler,
+            token: env::var("HTTP_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+        });
+
+    let listener = tokio::net::TcpListener::bind(&address).await?;
+    info!("Serving HTTP-API at {}.", address);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Answers with the error's message, like the bot does.
+struct ApiError(anyhow::Error);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        error!("Request failed: {}", self.0);
+        (StatusCode::INTERNAL_SERVER_ERROR, self.0.to_string()).into_response()
+    }
+}
+
+impl<E: Into<anyhow::Error>> From<E> for ApiError {
+    fn from(err: E) -> Self {
+        ApiError(err.into())
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+#[derive(Deserialize)]
+struct DaysQuery {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    #[serde(default)]
+    hourly: bool,
+}
+
+#[derive(Deserialize)]
+struct StatsQuery {
+    granularity: Option<Granularity>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+struct MonthQuery {
+    /// Any date of the month.
+    date: Option<NaiveDate>,
+}
+
+#[derive(Deserialize)]
+struct YearQuery {
+    year: Option<i32>,
+}
+
+/// The consumption and costs of a month or year, compared with the budgets.
+#[derive(Serialize)]
+struct Summary {
+    from: NaiveDate,
+    to: NaiveDate,
+    heating_consumption: f64,
+    general_consumption: f64,
+    heating_cost: f64,
+    general_cost: f64,
+    budget_heating: f64,
+    budget_general: f64,
+    months: Vec<Aggregate>,
+}
+
+impl Summary {
+    fn new(
+        from: NaiveDate,
+        to: NaiveDate,
+        budget_heating: f64,
+        budget_general: f64,
+        months: Vec<Aggregate>,
+    ) -> Self {
+        Summary {
+            from,
+            to,
+            heating_consumption: months
+                .iter()
+                .fold(0.0, |sum, month| sum + month.heating_sum),
+            general_consumption: months
+                .iter()
+                .fold(0.0, |sum, month| sum + month.general_sum),
+            heating_cost: months
+                .iter()
+                .fold(0.0, |sum, month| sum + month.heating_cost),
+            general_cost: months
+                .iter()
+                .fold(0.0, |sum, month| sum + month.general_cost),
+            budget_heating,
+            budget_general,
+            months,
+        }
+    }
+}
+
+/// `GET /powerfox/days?from&to&hourly`: the days with their costs, defaults to the current month.
+async fn days(
+    State(state): State<ApiState>,
+    Query(query): Query<DaysQuery>,
+) -> ApiResult<Vec<ExportDay>> {
+    let today = util::today();
+    let from = query.from.unwrap_or(util::first_of_month(today));
+    let to = query.to.unwrap_or(today);
+    Ok(Json(export::days(&state.db, from, to, query.hourly).await?))
+}
+
+/// `GET /powerfox/stats?granularity&from&to`: aggregates per period, defaults to the months of the current year.
+async fn stats(
+    State(state): State<ApiState>,
+    Query(query): Query<StatsQuery>,
+) -> ApiResult<Vec<Aggregate>> {
+    let today = util::today();
+    let from = query.from.unwrap_or(util::first_of_year(today));
+    let to = query.to.unwrap_or(today);
+    let granularity = query.granularity.unwrap_or(Granularity::Month);
+    Ok(Json(state.db.aggregate(from, to, granularity).await?))
+}
+
+/// `GET /powerfox/month?date`: the month containing the date, defaults to the current month.
+async fn month(
+    State(state): State<ApiState>,
+    Query(query): Query<MonthQuery>,
+) -> ApiResult<Summary> {
+    let from = util::first_of_month(query.date.unwrap_or(util::today()));
+    let to = from + Duration::days(util::days_in_month(from) as i64 - 1);
+    let config = state.db.get_config_at(from).await?;
+    let months = state.db.aggregate(from, to, Granularity::Month).await?;
+    Ok(Json(Summary::new(
+        from,
+        to,
+        config.monthly_budget_heating,
+        config.monthly_budget_general,
+        months,
+    )))
+}
+
+/// `GET /powerfox/year?year`: the months of the year, defaults to the current year.
+async fn year(State(state): State<ApiState>, Query(query): Query<YearQuery>) -> ApiResult<Summary> {
+    let year = query.year.unwrap_or(util::today().year());
+    let (from, to) = NaiveDate::from_ymd_opt(year, 1, 1)
+        .zip(NaiveDate::from_ymd_opt(year, 12, 31))
+        .ok_or(anyhow!("Invalid year {}.", year))?;
+    let configs = state.db.get_config_history().await?;
+    let months = state.db.aggregate(from, to, Granularity::Month).await?;
+    Ok(Json(Summary::new(
+        from,
+        to,
+        configs.yearly_budget_heating(year)?,
+        configs.yearly_budget_general(year)?,
+        months,
+    )))
+}
+
+/// `GET /powerfox/config`: the config valid today.
+async fn config(State(state): State<ApiState>) -> ApiResult<Config> {
+    Ok(Json(state.db.get_config().await?))
+}
+
+/// `GET /powerfox/config/history`: all versions of the config.
+async fn config_history(State(state): State<ApiState>) -> ApiResult<Vec<Config>> {
+    Ok(Json(state.db.get_config_history().await?.0))
+}
+
+/// `GET /powerfox/devices`: the devices linked to the Powerfox-account.
+async fn devices(State(state): State<ApiState>) -> ApiResult<Vec<Device>> {
+    Ok(Json(state.db.get_devices().await?))
+}
+
+/// `POST /powerfox/daily`: runs the daily job now, which sends the notifications as usual.
+/// Requires `HTTP_TOKEN` as bearer-token, see [authorize].
+async fn daily(State(state): State<ApiState>, headers: HeaderMap) -> Result<Response, ApiError> {
+    if let Err(rejection) = authorize(&headers, state.token.as_deref()) {
+        return Ok(rejection.into_response());
+    }
+    info!("Daily job triggered via HTTP.");
+    state.scheduler.run_daily(Trigger::Http).await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Why a request to `POST /powerfox/daily` is rejected, see [authorize].
+#[derive(Debug, PartialEq)]
+enum Rejection {
+    /// No token is configured, which disables the endpoint.
+    Disabled,
+    /// The request has no or a wrong bearer-token.
+    Unauthorized,
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        match self {
+            Rejection::Disabled => (
+                StatusCode::FORBIDDEN,
+                "This endpoint is disabled, set HTTP_TOKEN to enable it.",
+            )
+                .into_response(),
+            Rejection::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                "Missing or wrong bearer-token.",
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// Checks that the request has the token as bearer-token.
+fn authorize(headers: &HeaderMap, token: Option<&str>) -> Result<(), Rejection> {
+    let Some(token) = token else {
+        return Err(Rejection::Disabled);
+    };
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match given {
+        Some(given) if equal(given.as_bytes(), token.as_bytes()) => Ok(()),
+        _ => Err(Rejection::Unauthorized),
+    }
+}
+
+/// Compares in constant time, so the token can't be guessed from how long requests take.
+fn equal(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// `GET /metrics`: the metrics in Prometheus' text-format.
+async fn metrics(State(state): State<ApiState>) -> Result<String, ApiError> {
+    Ok(metrics::render(&state.db).await?)
+}
+
+/// `GET /health`: the result of each health-check, answering with 503 if any of them failed.
+async fn health(State(state): State<ApiState>) -> (StatusCode, Json<Health>) {
+    let health = Health::check(&state.db, &state.scheduler).await;
+    let status = if health.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(health))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, authorization.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn daily_requires_the_token() {
+        let token = Some("secret");
+        assert_eq!(authorize(&headers("Bearer secret"), token), Ok(()));
+        for authorization in ["Bearer secrets", "Bearer ", "secret", "Basic secret"] {
+            assert_eq!(
+                authorize(&headers(authorization), token),
+                Err(Rejection::Unauthorized)
+            );
+        }
+        assert_eq!(
+            authorize(&HeaderMap::new(), token),
+            Err(Rejection::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn daily_is_disabled_without_a_token() {
+        assert_eq!(
+            authorize(&headers("Bearer "), None),
+            Err(Rejection::Disabled)
+        );
+    }
+}
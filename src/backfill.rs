@@ -0,0 +1,110 @@
+use crate::{
+    cli::BackfillArgs,
+    db::{Db, Trigger},
+    fetch,
+    meteo::Meteo,
+    util,
+};
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use log::{info, warn};
+use std::collections::{BTreeMap, HashSet};
+
+/// The name under which the runs of `backfill` are saved.
+const BACKFILL: &str = "backfill";
+
+/// Runs the `backfill`-subcommand.
+///
+/// Days that can't be imported are skipped, so the remaining days are still imported - they are listed at the end.
+pub async fn run(db: &Db, args: BackfillArgs) -> Result<()> {
+    let today = util::today();
+    let from = args.from;
+    let to = args.to.unwrap_or(util::yesterday());
+    if from > to {
+        bail!("--from {} is after --to {}.", from, to);
+    }
+    if to >= today {
+        bail!(
+            "Only finished days can be imported, so --to needs to be before {}.",
+            today
+        );
+    }
+
+    let stored: HashSet<NaiveDate> = db
+        .get_days_between(from, to)
+        .await?
+        .0
+        .iter()
+        .map(|day| day.date)
+        .collect();
+    let dates: Vec<NaiveDate> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .filter(|date| args.overwrite || !stored.contains(date))
+        .collect();
+    if dates.is_empty() {
+        info!("All days from {} to {} are stored already.", from, to);
+        return Ok(());
+    }
+    info!("Importing {} days from {} to {}.", dates.len(), from, to);
+
+    // the archive reaches back for decades but has no temperatures for the last few days, only those come from the forecast
+    let meteo = Meteo::new()?;
+    let run = db.start_job_run(BACKFILL, Trigger::Cli).await?;
+    let archived = match meteo.get_daily_temperatures(from, to).await {
+        Ok(temperatures) => temperatures,
+        Err(err) => {
+            warn!(
+                "Could not get temperatures from the weather-archive: {}",
+                err
+            );
+            BTreeMap::new()
+        }
+    };
+
+    let mut imported = Vec::new();
+    let mut failed = Vec::new();
+    for date in &dates {
+        let result = async {
+            let temperature = match archived.get(date) {
+                Some(temperature) => *temperature,
+                None => meteo
+                    .get_temperature_for(*date)
+                    .await?
+                    .average_temperature()?,
+            };
+            fetch::import_day(db, *date, temperature).await
+        }
+        .await;
+        match result {
+            Ok(day) => {
+                info!(
+                    "Imported {}: {:.2} kWh heating, {:.2} kWh general.",
+                    date, day.heating_consumption, day.general_consumption
+                );
+                imported.push(*date);
+            }
+            Err(err) => {
+                warn!("Could not import {}: {}", date, err);
+                failed.push(date.to_string());
+            }
+        }
+    }
+
+    let error = if failed.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "Could not import {} of {} days: {}",
+            failed.len(),
+            dates.len(),
+            failed.join(", ")
+        ))
+    };
+    db.finish_job_run(run, error.clone(), imported).await?;
+    if let Some(error) = error {
+        bail!(error);
+    }
+    info!("Imported {} days.", dates.len());
+    Ok(())
+}
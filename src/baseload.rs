@@ -0,0 +1,185 @@
+use crate::db::{Config, Hour};
+use chrono::{Datelike, Duration, NaiveDate, Timelike};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+
+/// The night, in hours of the local time, during which hardly anything but always-on devices consumes power.
+const NIGHT: std::ops::Range<u32> = 0..6;
+
+/// How many consecutive hours the consumption has to stay low to count as baseload,
+/// so a single quiet hour doesn't hide e.g. a fridge's cycle.
+const SUSTAINED_HOURS: usize = 3;
+
+const HOURS_PER_YEAR: f64 = 24.0 * 365.0;
+
+/// The baseload of the general meter on a day, see [per_day].
+pub struct Baseload {
+    pub date: NaiveDate,
+    /// The power in kW, which equals the average consumption in kWh per hour.
+    pub power: f64,
+}
+
+impl Baseload {
+    /// What the baseload would cost in a year at the config's cost of the general meter.
+    pub fn yearly_cost(&self, config: &Config) -> f64 {
+        self.power * HOURS_PER_YEAR * config.cost_general
+    }
+}
+
+/// Calculates the baseload of every day with enough hours at night: the lowest average general consumption
+/// over [SUSTAINED_HOURS] consecutive hours of the night in the timezone.
+pub fn per_day(hours: &[Hour], timezone: Tz) -> Vec<Baseload> {
+    let mut nights: BTreeMap<NaiveDate, Vec<&Hour>> = BTreeMap::new();
+    for hour in hours {
+        if NIGHT.contains(&hour.timestamp.with_timezone(&timezone).hour()) {
+            nights.entry(hour.date).or_default().push(hour);
+        }
+    }
+
+    nights
+        .into_iter()
+        .filter_map(|(date, mut night)| {
+            night.sort_by_key(|hour| hour.timestamp);
+            night
+                .windows(SUSTAINED_HOURS)
+                // only consecutive hours, in case some are missing
+                .filter(|window| {
+                    (window[SUSTAINED_HOURS - 1].timestamp - window[0].timestamp).num_hours()
+                        == SUSTAINED_HOURS as i64 - 1
+                })
+                .map(|window| {
+                    window
+                        .iter()
+                        .fold(0.0, |sum, hour| sum + hour.general_consumption)
+                        / SUSTAINED_HOURS as f64
+                })
+                .min_by(f64::total_cmp)
+                .map(|power| Baseload { date, power })
+        })
+        .collect()
+}
+
+/// Averages the baseloads per week, starting on Monday.
+pub fn per_week(days: &[Baseload]) -> Vec<Baseload> {
+    let mut weeks: BTreeMap<NaiveDate, Vec<f64>> = BTreeMap::new();
+    for day in days {
+        let monday = day.date - Duration::days(day.date.weekday().num_days_from_monday().into());
+        weeks.entry(monday).or_default().push(day.power);
+    }
+    weeks
+        .into_iter()
+        .map(|(date, powers)| Baseload {
+            date,
+            power: powers.iter().fold(0.0, |sum, power| sum + power) / powers.len() as f64,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeZone, Utc};
+    use chrono_tz::Europe::Berlin;
+
+    /// An hour starting at the UTC-time, with the date it has in Berlin.
+    fn hour(timestamp: DateTime<Utc>, general_consumption: f64) -> Hour {
+        Hour {
+            timestamp,
+            date: timestamp.with_timezone(&Berlin).date_naive(),
+            heating_consumption: 1.0,
+            general_consumption,
+        }
+    }
+
+    /// The hours of the night before the date in Berlin, starting at the local hours.
+    fn night(date: NaiveDate, consumption: &[(u32, f64)]) -> Vec<Hour> {
+        consumption
+            .iter()
+            .map(|(local, general)| {
+                let start = Berlin
+                    .from_local_datetime(&date.and_hms_opt(*local, 0, 0).unwrap())
+                    .unwrap();
+                hour(start.with_timezone(&Utc), *general)
+            })
+            .collect()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, day).unwrap()
+    }
+
+    #[test]
+    fn baseload_is_the_lowest_sustained_consumption() {
+        // a single quiet hour doesn't count
+        let hours = night(
+            date(15),
+            &[(0, 0.3), (1, 0.1), (2, 0.2), (3, 0.3), (4, 0.6), (5, 0.01)],
+        );
+        let days = per_day(&hours, Berlin);
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].date, date(15));
+        assert!((days[0].power - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn nights_without_three_consecutive_hours_have_no_baseload() {
+        let short = night(date(15), &[(1, 0.1), (2, 0.1)]);
+        assert!(per_day(&short, Berlin).is_empty());
+        let gaps = night(date(16), &[(0, 0.1), (1, 0.1), (3, 0.1), (4, 0.1)]);
+        assert!(per_day(&gaps, Berlin).is_empty());
+    }
+
+    #[test]
+    fn night_follows_the_local_time() {
+        // 22:00 to 05:00 UTC is 23:00 to 06:00 in Berlin, so neither the first nor the last hour is at night
+        let hours: Vec<Hour> = (0..8)
+            .map(|offset| {
+                let timestamp =
+                    Utc.with_ymd_and_hms(2026, 1, 14, 22, 0, 0).unwrap() + Duration::hours(offset);
+                let general = if offset == 0 || offset == 7 {
+                    0.01
+                } else {
+                    0.3
+                };
+                hour(timestamp, general)
+            })
+            .collect();
+        let days = per_day(&hours, Berlin);
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].date, date(15));
+        assert!((days[0].power - 0.3).abs() < 1e-9);
+
+        // in UTC, the night ends with the quiet hours
+        let days = per_day(&hours, Tz::UTC);
+        assert!((days[0].power - (0.3 + 0.3 + 0.01) / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hours_are_consecutive_across_a_dst_switch() {
+        // there's no 02:00 when the clocks are set forward
+        let date = NaiveDate::from_ymd_opt(2026, 3, 29).unwrap();
+        let hours = night(date, &[(1, 0.3), (3, 0.2), (4, 0.2), (5, 0.4)]);
+        let days = per_day(&hours, Berlin);
+        assert_eq!(days.len(), 1);
+        assert!((days[0].power - (0.3 + 0.2 + 0.2) / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        let days: Vec<Baseload> = [(11, 0.1), (12, 0.2), (13, 0.4), (18, 0.3)]
+            .into_iter()
+            .map(|(day, power)| Baseload {
+                date: date(day),
+                power,
+            })
+            .collect();
+        // the 11th is a Sunday, the 18th the Sunday after
+        let weeks = per_week(&days);
+        assert_eq!(
+            weeks.iter().map(|week| week.date).collect::<Vec<_>>(),
+            vec![date(5), date(12)]
+        );
+        assert!((weeks[0].power - 0.1).abs() < 1e-9);
+        assert!((weeks[1].power - 0.3).abs() < 1e-9);
+    }
+}
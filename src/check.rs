@@ -0,0 +1,73 @@
+use crate::{db, matrix, meteo::Meteo, notify::Notifiers, powerfox::Powerfox, telegram};
+use ::serenity::all::Http;
+use anyhow::{bail, Result};
+use std::{env, future::Future};
+
+/// Runs the `check`-subcommand: validates the settings and connects to the database and every configured API,
+/// printing the result of each check. Fails if any of them failed.
+pub async fn run() -> Result<()> {
+    let mut results = vec![
+        check("database", async {
+            let config = db::connect().await?.get_config().await?;
+            Ok(format!("config valid from {}", config.valid_from))
+        })
+        .await,
+        check("powerfox", async {
+            let devices = Powerfox::new()?.get_devices().await?;
+            Ok(format!("{} devices", devices.len()))
+        })
+        .await,
+        check("weather", async {
+            let temperature = Meteo::new()?
+                .get_temperature_for_today()
+                .await?
+                .average_temperature()?;
+            Ok(format!("{:.1} °C today", temperature))
+        })
+        .await,
+        check("notifiers", async {
+            Notifiers::from_env()?;
+            Ok("configured".to_string())
+        })
+        .await,
+    ];
+
+    for frontend in crate::frontends() {
+        let result = match frontend.as_str() {
+            "discord" => {
+                check("discord", async {
+                    let user = Http::new(&env::var("DISCORD_TOKEN")?)
+                        .get_current_user()
+                        .await?;
+                    Ok(format!("logged in as {}", user.name))
+                })
+                .await
+            }
+            "matrix" => check("matrix", matrix::check()).await,
+            "telegram" => check("telegram", telegram::check()).await,
+            _ => check(&frontend, async { bail!("unknown frontend") }).await,
+        };
+        results.push(result);
+    }
+
+    let failed = results.iter().filter(|success| !**success).count();
+    if failed > 0 {
+        bail!("{} of {} checks failed.", failed, results.len());
+    }
+    println!("All {} checks passed.", results.len());
+    Ok(())
+}
+
+/// Prints the result of the check and returns whether it succeeded.
+async fn check(name: &str, check: impl Future<Output = Result<String>>) -> bool {
+    match check.await {
+        Ok(detail) => {
+            println!("ok      {}: {}", name, detail);
+            true
+        }
+        Err(err) => {
+            println!("FAILED  {}: {}", name, err);
+            false
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{commands::Setting, export::Format};
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Reads your power-consumption, calculates costs and informs via Discord, Matrix, Telegram and more.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Defaults to `serve`.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Start the bot and the scheduled daily summary.
+    Serve,
+    /// Export the stored days as CSV or JSON.
+    Export(ExportArgs),
+    /// Import historical days from a CSV-file with one value per row.
+    Import(ImportArgs),
+    /// Run the daily job once: import a day and send its summary, without Discord's bot or the scheduler.
+    RunDaily(RunDailyArgs),
+    /// Import all days of a range from Powerfox that aren't stored yet.
+    Backfill(BackfillArgs),
+    /// Show or change the config.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Show the devices linked to the Powerfox-account.
+    #[command(subcommand)]
+    Devices(DevicesCommand),
+    /// Check the settings and the connections to the database and the APIs.
+    Check,
+    /// Ask the running server for its health, failing unless it's healthy.
+    Health(HealthArgs),
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// The first day to export (YYYY-MM-DD), defaults to the start of the month.
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+
+    /// The last day to export (YYYY-MM-DD), defaults to today.
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    pub format: Format,
+
+    /// Include the hourly values - as CSV, this exports one row per hour instead of per day.
+    #[arg(long)]
+    pub hourly: bool,
+
+    /// The file to write to, defaults to stdout.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// The CSV-file to import.
+    pub file: PathBuf,
+
+    /// The column containing the date of each value.
+    #[arg(long, default_value = "date")]
+    pub date_column: String,
+
+    /// The column containing the meter of each value.
+    #[arg(long, default_value = "meter")]
+    pub meter_column: String,
+
+    /// The column containing the kWh - or the meter reading with `--cumulative`.
+    #[arg(long, default_value = "value")]
+    pub value_column: String,
+
+    /// The column containing the average temperature of each day.
+    /// Without it, temperatures are fetched from the weather-archive.
+    #[arg(long)]
+    pub temperature_column: Option<String>,
+
+    /// The name of the heating-meter in the meter-column.
+    #[arg(long, default_value = "heating")]
+    pub heating_meter: String,
+
+    /// The name of the general meter in the meter-column.
+    #[arg(long, default_value = "general")]
+    pub general_meter: String,
+
+    /// The values are meter readings at the end of each day, which are converted into daily consumption.
+    #[arg(long)]
+    pub cumulative: bool,
+
+    /// The format of the dates, see https://docs.rs/chrono/latest/chrono/format/strftime/index.html.
+    #[arg(long, default_value = "%Y-%m-%d")]
+    pub date_format: String,
+
+    #[arg(long, default_value_t = ',')]
+    pub delimiter: char,
+
+    /// Replace days that are already stored instead of aborting.
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+#[derive(Args)]
+pub struct RunDailyArgs {
+    /// The day to import and summarize (YYYY-MM-DD), defaults to yesterday.
+    #[arg(long)]
+    pub date: Option<NaiveDate>,
+    /// Fetch and summarize the day, but only print the messages and what would be saved.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct BackfillArgs {
+    /// The first day to import (YYYY-MM-DD).
+    #[arg(long)]
+    pub from: NaiveDate,
+
+    /// The last day to import (YYYY-MM-DD), defaults to yesterday.
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+
+    /// Import days that are already stored again instead of skipping them.
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Show the config valid today and the schedule of the daily summary.
+    Get {
+        /// Show all versions of the config instead.
+        #[arg(long)]
+        history: bool,
+    },
+    /// Change a config-value from the given date on.
+    Set {
+        setting: Setting,
+        value: String,
+
+        /// The date from which the value applies (YYYY-MM-DD), defaults to today.
+        #[arg(long)]
+        valid_from: Option<NaiveDate>,
+    },
+    /// Change the schedule of the daily summary - a running server uses it once it's restarted.
+    Schedule {
+        /// Cron-expression with seconds: sec min hour day month weekday [year].
+        cron: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DevicesCommand {
+    /// List the stored devices.
+    List {
+        /// Get the devices from Powerfox and store them first.
+        #[arg(long)]
+        refresh: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct HealthArgs {
+    /// The URL of the health-endpoint, defaults to /health at `HTTP_ADDRESS`.
+    #[arg(long)]
+    pub url: Option<String>,
+}
@@ -0,0 +1,738 @@
+use crate::{
+    baseload,
+    db::{Config, Db, Granularity, Trigger},
+    export::{self, Format},
+    fetch, profile,
+    scheduler::{self, Scheduler},
+    util,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::{Datelike, Duration, NaiveDate};
+use log::info;
+use std::{env, fmt::Display};
+
+/// The name under which runs of [yesterday] are saved, see [scheduler::record_run].
+const YESTERDAY: &str = "yesterday";
+
+/// The number of weeks shown by [baseload].
+const BASELOAD_WEEKS: i64 = 12;
+
+/// The number of days [profile] averages over.
+const PROFILE_DAYS: i64 = 28;
+
+/// The number of days whose peaks are listed by [profile].
+const PEAK_DAYS: usize = 7;
+
+/// The maximum length of the bars drawn by [profile].
+const BAR_WIDTH: usize = 12;
+
+/// The number of runs shown by [status].
+const RECENT_RUNS: i64 = 10;
+
+/// The maximum length of a message on every frontend - Discord's is the shortest,
+/// see [Discord's docs](https://discord.com/developers/docs/resources/channel#create-message).
+const MESSAGE_LIMIT: usize = 2000;
+
+/// The commands of the text-based frontends, see [run_text].
+const HELP: &str = "Commands:
+/yesterday - yesterday's heating-info
+/today - today's heating-info
+/month - this month's costs
+/year - this year's costs
+/stats [day|week|month] [from] [to] - consumption-statistics per period
+/export [csv|json] [hourly] [from] [to] - the days as a file, this month by default
+/budgets - the configured budgets
+/costs - the configured costs
+/history - all versions of the config
+/schedule - the schedule of the daily summary
+/status - the recent runs of the jobs and the next daily run
+/baseload - the consumption of always-on devices and its yearly cost
+/profile - the average consumption per hour of each weekday and the daily peaks
+/devices - the devices linked to the Powerfox-account
+/version - the bot's version
+/help - this message
+/config cost|budget|fee heating|general <value> [valid from] - update a config-value
+/config schedule <cron> - update the schedule of the daily summary
+Dates are written as YYYY-MM-DD.";
+
+/// The answer to a command, see [run_text].
+pub struct Answer {
+    pub text: String,
+    pub file: Option<Attachment>,
+}
+
+/// A file sent along with an answer, e.g. an export.
+pub struct Attachment {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl From<String> for Answer {
+    fn from(text: String) -> Self {
+        Answer { text, file: None }
+    }
+}
+
+/// A config-value that can be changed with `/config`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Setting {
+    CostHeating,
+    CostGeneral,
+    BudgetHeating,
+    BudgetGeneral,
+    FeeHeating,
+    FeeGeneral,
+}
+
+impl Setting {
+    fn name(&self) -> &'static str {
+        match self {
+            Setting::CostHeating => "heating-cost",
+            Setting::CostGeneral => "general-cost",
+            Setting::BudgetHeating => "heating-budget",
+            Setting::BudgetGeneral => "general-budget",
+            Setting::FeeHeating => "heating-fee",
+            Setting::FeeGeneral => "general-fee",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Setting::CostHeating | Setting::CostGeneral => "€/kWh",
+            Setting::FeeHeating | Setting::FeeGeneral => "€/month",
+            _ => "€",
+        }
+    }
+
+    pub fn get(&self, config: &Config) -> f64 {
+        match self {
+            Setting::CostHeating => config.cost_heating,
+            Setting::CostGeneral => config.cost_general,
+            Setting::BudgetHeating => config.monthly_budget_heating,
+            Setting::BudgetGeneral => config.monthly_budget_general,
+            Setting::FeeHeating => config.base_fee_heating,
+            Setting::FeeGeneral => config.base_fee_general,
+        }
+    }
+
+    fn set(&self, config: Config, value: f64) -> Config {
+        match self {
+            Setting::CostHeating => config.with_cost_heating(value),
+            Setting::CostGeneral => config.with_cost_general(value),
+            Setting::BudgetHeating => config.with_monthly_budget_heating(value),
+            Setting::BudgetGeneral => config.with_monthly_budget_general(value),
+            Setting::FeeHeating => config.with_base_fee_heating(value),
+            Setting::FeeGeneral => config.with_base_fee_general(value),
+        }
+    }
+
+    /// Parses a new value, which has to be a finite number that isn't negative.
+    fn parse_value(&self, value: &str) -> Result<f64> {
+        match value.trim().parse::<f64>() {
+            Ok(parsed) if parsed.is_finite() && parsed >= 0.0 => Ok(parsed),
+            _ => bail!(
+                "The {} has to be a number of at least 0 in {}, not '{}'.",
+                self.name(),
+                self.unit(),
+                value
+            ),
+        }
+    }
+
+    /// Parses e.g. `cost heating`.
+    fn parse(kind: &str, meter: &str) -> Result<Self> {
+        Ok(match (kind, meter) {
+            ("cost", "heating") => Setting::CostHeating,
+            ("cost", "general") => Setting::CostGeneral,
+            ("budget", "heating") => Setting::BudgetHeating,
+            ("budget", "general") => Setting::BudgetGeneral,
+            ("fee", "heating") => Setting::FeeHeating,
+            ("fee", "general") => Setting::FeeGeneral,
+            _ => bail!("Unknown config-value '{} {}'.", kind, meter),
+        })
+    }
+}
+
+/// Parses an optional date-argument (YYYY-MM-DD), using the default if it's missing.
+pub fn parse_date(date: Option<String>, default: NaiveDate) -> Result<NaiveDate> {
+    match date {
+        Some(date) => Ok(NaiveDate::parse_from_str(&date, "%Y-%m-%d")?),
+        None => Ok(default),
+    }
+}
+
+/// Joins the lines with newlines, leaving out the last lines if the message would be too long.
+/// The limit is in characters, e.g. the bars and shades take up several bytes each.
+fn join_limited(lines: &[String]) -> String {
+    let mut message = String::new();
+    let mut length = 0;
+    for (index, line) in lines.iter().enumerate() {
+        let omitted = format!("\n... and {} more.", lines.len() - index);
+        let line_length = line.chars().count();
+        if length + line_length + omitted.chars().count() + 1 > MESSAGE_LIMIT {
+            message.push_str(&omitted);
+            break;
+        }
+        if !message.is_empty() {
+            message.push('\n');
+            length += 1;
+        }
+        message.push_str(line);
+        length += line_length;
+    }
+    message
+}
+
+/// Logs who changed which config-value.
+fn log_change(author: &str, value: &str, old: impl Display, new: impl Display) {
+    info!("{} changed {} from {} to {}.", author, value, old, new);
+}
+
+pub fn version() -> String {
+    format!(
+        "The bot-version is {}.",
+        env::var("CARGO_PKG_VERSION").unwrap_or("<could not find version>".to_string())
+    )
+}
+
+pub async fn yesterday(db: &Db) -> Result<String> {
+    let date = util::yesterday();
+    let yesterday = scheduler::record_run(
+        db,
+        YESTERDAY,
+        Trigger::Chat,
+        date,
+        fetch::create_day(db, date),
+    )
+    .await??;
+    let config = db.get_config_at(yesterday.date).await?;
+    Ok(yesterday.summary(&config))
+}
+
+pub async fn today(db: &Db) -> Result<String> {
+    let config = db.get_config().await?;
+    let day = fetch::get_today(db).await?;
+    // because this takes some time, this log is used to check if the task is completed
+    info!("Done with /today");
+    Ok(day.summary(&config))
+}
+
+pub async fn budgets(db: &Db) -> Result<String> {
+    let config = db.get_config().await?;
+    Ok(format!(
+        "Heating-Budget: {}€\nGeneral Budget: {}€\nValid from {}, see /history for previous values.",
+        config.monthly_budget_heating, config.monthly_budget_general, config.valid_from
+    ))
+}
+
+pub async fn costs(db: &Db) -> Result<String> {
+    let config = db.get_config().await?;
+    Ok(format!(
+        "Heating-Cost: {}€/kWh + {}€/month\nGeneral Cost: {}€/kWh + {}€/month\nValid from {}, see /history for previous values.",
+        config.cost_heating,
+        config.base_fee_heating,
+        config.cost_general,
+        config.base_fee_general,
+        config.valid_from
+    ))
+}
+
+pub async fn history(db: &Db) -> Result<String> {
+    let configs = db.get_config_history().await?;
+    let mut message = String::from(
+        "Valid from: heating-cost, general-cost, heating-budget, general-budget, heating-fee, general-fee",
+    );
+    for config in configs.iter() {
+        message.push_str(&format!(
+            "\n{}: {}€, {}€, {}€, {}€, {}€, {}€",
+            config.valid_from,
+            config.cost_heating,
+            config.cost_general,
+            config.monthly_budget_heating,
+            config.monthly_budget_general,
+            config.base_fee_heating,
+            config.base_fee_general
+        ));
+    }
+    Ok(message)
+}
+
+pub async fn schedule(db: &Db, scheduler: &Scheduler) -> Result<String> {
+    let cron = Scheduler::daily_schedule(db).await?;
+    let next = match scheduler.next_daily().await? {
+        Some(next) => next.format("%Y-%m-%d %H:%M %Z").to_string(),
+        None => "<not scheduled>".to_string(),
+    };
+    Ok(format!(
+        "Daily schedule: `{}` ({})\nNext run: {}",
+        cron,
+        util::timezone(),
+        next
+    ))
+}
+
+pub async fn status(db: &Db, scheduler: &Scheduler) -> Result<String> {
+    let next = match scheduler.next_daily().await? {
+        Some(next) => next.format("%Y-%m-%d %H:%M %Z").to_string(),
+        None => "<not scheduled>".to_string(),
+    };
+    let runs = db.get_job_runs(RECENT_RUNS).await?;
+    if runs.is_empty() {
+        return Ok(format!("Next daily run: {}\nNo runs yet.", next));
+    }
+
+    let mut lines = vec![
+        format!("Next daily run: {}", next),
+        "Recent runs:".to_string(),
+    ];
+    let timezone = util::timezone();
+    for run in runs {
+        let mut line = format!(
+            "- {} {} ({}): {}",
+            run.started_at
+                .with_timezone(&timezone)
+                .format("%Y-%m-%d %H:%M"),
+            run.job,
+            run.triggered_by,
+            run.status
+        );
+        if let Some(finished_at) = run.finished_at {
+            let seconds = (finished_at - run.started_at).num_seconds();
+            line.push_str(&format!(" after {} s", seconds));
+        }
+        if !run.days.is_empty() {
+            line.push_str(&format!(", wrote {}", run.days.replace(',', ", ")));
+        }
+        if let Some(error) = run.error {
+            line.push_str(&format!(" - {}", error));
+        }
+        lines.push(line);
+    }
+    Ok(join_limited(&lines))
+}
+
+pub async fn baseload(db: &Db) -> Result<String> {
+    let today = util::today();
+    let hours = db
+        .get_hours_between(today - Duration::weeks(BASELOAD_WEEKS), today)
+        .await?;
+    let days = baseload::per_day(&hours, util::timezone());
+    let Some(latest) = days.last() else {
+        return Ok(
+            "No hourly values at night yet, the baseload is calculated from them.".to_string(),
+        );
+    };
+    let config = db.get_config().await?;
+
+    let mut lines = vec![
+        "Baseload - the lowest consumption of the general meter at night:".to_string(),
+        format!(
+            "Latest: {:.3} kW on {}, which costs {:.2}€ per year.",
+            latest.power,
+            latest.date,
+            latest.yearly_cost(&config)
+        ),
+        "Per week:".to_string(),
+    ];
+    for week in baseload::per_week(&days).iter().rev() {
+        lines.push(format!(
+            "- {}: {:.3} kW, {:.2}€/year",
+            week.date,
+            week.power,
+            week.yearly_cost(&config)
+        ));
+    }
+    Ok(join_limited(&lines))
+}
+
+pub async fn profile(db: &Db) -> Result<String> {
+    let today = util::today();
+    let from = today - Duration::days(PROFILE_DAYS);
+    let hours = db.get_hours_between(from, today).await?;
+    if hours.is_empty() {
+        return Ok(format!("No hourly values since {}.", from));
+    }
+    let peaks = db.get_peaks_between(from, today).await?;
+
+    let timezone = util::timezone();
+    let by_hour = profile::by_hour(&hours, timezone);
+    let max = by_hour
+        .iter()
+        .fold(0.0, |max: f64, hour| max.max(hour.total()));
+    let mut lines = vec![
+        format!("Load profile since {} - kWh heating/general:", from),
+        "Average per hour:".to_string(),
+        "```".to_string(),
+    ];
+    for (hour, average) in by_hour.iter().enumerate() {
+        lines.push(format!(
+            "{:02}h {:<width$} {:.2}/{:.2}",
+            hour,
+            profile::bar(average.total(), max, BAR_WIDTH),
+            average.heating,
+            average.general,
+            width = BAR_WIDTH
+        ));
+    }
+    lines.push("```".to_string());
+
+    let by_weekday = profile::by_weekday(&hours, timezone);
+    let max = by_weekday
+        .iter()
+        .flat_map(|(_, averages)| averages)
+        .fold(0.0, |max: f64, hour| max.max(hour.total()));
+    lines.push("Average per hour of each weekday, darker is more, and per day:".to_string());
+    lines.push("```".to_string());
+    lines.push("    00    06    12    18".to_string());
+    for (weekday, averages) in by_weekday {
+        let shades: String = averages
+            .iter()
+            .map(|average| profile::shade(average.total(), max))
+            .collect();
+        let (heating, general) = averages
+            .iter()
+            .fold((0.0, 0.0), |(heating, general), average| {
+                (heating + average.heating, general + average.general)
+            });
+        lines.push(format!(
+            "{} {} {:.1}/{:.1}",
+            weekday, shades, heating, general
+        ));
+    }
+    lines.push("```".to_string());
+
+    lines.push("Peak hour per day, highest report-value heating/general:".to_string());
+    let peak_hours = profile::peak_hours(&hours, timezone);
+    for peak_hour in peak_hours.iter().rev().take(PEAK_DAYS) {
+        let mut line = format!(
+            "- {}: {:02}h with {:.2}",
+            peak_hour.date, peak_hour.hour, peak_hour.consumption
+        );
+        if let Some(peak) = peaks.iter().find(|peak| peak.date == peak_hour.date) {
+            line.push_str(&format!(
+                ", {:.2}/{:.2}",
+                peak.heating_max, peak.general_max
+            ));
+        }
+        lines.push(line);
+    }
+    if let (Some(heating), Some(general)) = (
+        peaks
+            .iter()
+            .max_by(|a, b| a.heating_max.total_cmp(&b.heating_max)),
+        peaks
+            .iter()
+            .max_by(|a, b| a.general_max.total_cmp(&b.general_max)),
+    ) {
+        lines.push(format!(
+            "Highest report-values: heating {:.2} on {}, general {:.2} on {}",
+            heating.heating_max, heating.date, general.general_max, general.date
+        ));
+    }
+    Ok(join_limited(&lines))
+}
+
+/// Exports the days between both dates as a file, see [export::export].
+/// Defaults to CSV without hours from the start of the month until today.
+pub async fn export(
+    db: &Db,
+    format: Option<Format>,
+    from: Option<String>,
+    to: Option<String>,
+    hourly: bool,
+) -> Result<Answer> {
+    let today = util::today();
+    let from = parse_date(from, util::first_of_month(today))?;
+    let to = parse_date(to, today)?;
+    let format = format.unwrap_or(Format::Csv);
+    let data = export::export(db, from, to, format, hourly).await?;
+
+    let name = format!(
+        "powerfox_{}_{}{}.{}",
+        from,
+        to,
+        if hourly { "_hourly" } else { "" },
+        format.extension()
+    );
+    Ok(Answer {
+        text: format!("Here's your data from {} to {}.", from, to),
+        file: Some(Attachment { name, data }),
+    })
+}
+
+pub async fn devices(db: &Db) -> Result<String> {
+    let devices = db.get_devices().await?;
+    if devices.is_empty() {
+        return Ok("No devices yet, they are saved when data is fetched.".to_string());
+    }
+    let mut message = String::from("Devices:");
+    for device in devices {
+        message.push_str(&format!("\n{} (`{}`)", device.name, device.device_id));
+        if device.main_device {
+            message.push_str(" - main device");
+        }
+    }
+    Ok(message)
+}
+
+pub async fn month(db: &Db) -> Result<String> {
+    let config = db.get_config().await?;
+    let today = util::today();
+    let aggregates = db
+        .aggregate(util::first_of_month(today), today, Granularity::Month)
+        .await?;
+
+    Ok(match aggregates.first() {
+        None => "No data for the current month.".to_string(),
+        Some(month) => format!(
+            "Cost of Heating: {:.2}€/{}€\nGeneral cost: {:.2}€/{}€",
+            month.heating_cost,
+            config.monthly_budget_heating,
+            month.general_cost,
+            config.monthly_budget_general
+        ),
+    })
+}
+
+pub async fn year(db: &Db) -> Result<String> {
+    let configs = db.get_config_history().await?;
+    let today = util::today();
+    let months = db
+        .aggregate(util::first_of_year(today), today, Granularity::Month)
+        .await?;
+
+    if months.is_empty() {
+        return Ok("No data for the current year.".to_string());
+    }
+    Ok(format!(
+        "Cost of Heating: {:.2}€/{}€\nGeneral cost: {:.2}€/{}€",
+        months.iter().map(|month| month.heating_cost).sum::<f64>(),
+        configs.yearly_budget_heating(today.year())?,
+        months.iter().map(|month| month.general_cost).sum::<f64>(),
+        configs.yearly_budget_general(today.year())?
+    ))
+}
+
+/// Defaults to months of the current year.
+pub async fn stats(
+    db: &Db,
+    granularity: Option<Granularity>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<String> {
+    let today = util::today();
+    let from = parse_date(from, util::first_of_year(today))?;
+    let to = parse_date(to, today)?;
+    let aggregates = db
+        .aggregate(from, to, granularity.unwrap_or(Granularity::Month))
+        .await?;
+
+    if aggregates.is_empty() {
+        return Ok(format!("No data from {} to {}.", from, to));
+    }
+
+    // heating and general: sum (average/min/max per day), cost
+    let mut lines = vec![format!(
+        "From {} to {} - kWh sum (avg/min/max per day), cost:",
+        from, to
+    )];
+    for aggregate in &aggregates {
+        lines.push(format!(
+            "**{}** ({} days, {:.1} °C)\n> Heating: {:.1} ({:.1}/{:.1}/{:.1}), {:.2}€\n> General: {:.1} ({:.1}/{:.1}/{:.1}), {:.2}€",
+            aggregate.period,
+            aggregate.days,
+            aggregate.average_temperature,
+            aggregate.heating_sum,
+            aggregate.heating_avg,
+            aggregate.heating_min,
+            aggregate.heating_max,
+            aggregate.heating_cost,
+            aggregate.general_sum,
+            aggregate.general_avg,
+            aggregate.general_min,
+            aggregate.general_max,
+            aggregate.general_cost
+        ));
+    }
+    Ok(join_limited(&lines))
+}
+
+/// Updates the config-value from the given date on, defaulting to today. The author is only logged.
+pub async fn set(
+    db: &Db,
+    setting: Setting,
+    value: &str,
+    valid_from: Option<String>,
+    author: &str,
+) -> Result<String> {
+    let value = setting.parse_value(value)?;
+    let valid_from = parse_date(valid_from, util::today())?;
+    let config = db.get_config_at(valid_from).await?;
+    let old = setting.get(&config);
+    let config = setting.set(config.with_valid_from(valid_from), value);
+    let config = db.save_config(config).await?;
+    let new = setting.get(&config);
+    log_change(
+        author,
+        &format!("{} valid from {}", setting.name(), valid_from),
+        old,
+        new,
+    );
+    Ok(format!(
+        "Updated {} to {}{}, valid from {}.",
+        setting.name(),
+        new,
+        setting.unit(),
+        config.valid_from
+    ))
+}
+
+/// Updates the schedule of the daily summary. The author is only logged.
+pub async fn set_schedule(
+    db: &Db,
+    scheduler: &Scheduler,
+    cron: &str,
+    author: &str,
+) -> Result<String> {
+    let old = Scheduler::daily_schedule(db).await?;
+    scheduler.reschedule_daily(cron).await?;
+    log_change(author, "daily schedule", old, cron);
+    Ok(format!(
+        "Updated daily schedule to `{}` ({}).",
+        cron,
+        util::timezone()
+    ))
+}
+
+/// Runs a command written as text, e.g. `/stats week 2024-01-01`, for the frontends without native commands.
+/// Returns `None` if the text isn't a command, errors are part of the answer.
+/// Answers are written in Discord's markdown, see [markup](crate::markup) for the other frontends.
+/// Only authors who may configure can use `/config`, the author is only logged.
+pub async fn run_text(
+    db: &Db,
+    scheduler: &Scheduler,
+    text: &str,
+    author: &str,
+    may_configure: bool,
+) -> Option<Answer> {
+    let text = text.trim().strip_prefix('/')?;
+    let mut args = text.split_whitespace();
+    // Telegram appends the bot's name in groups, e.g. `/today@powerfox_bot`
+    let command = args.next()?.split('@').next()?.to_lowercase();
+    let args: Vec<String> = args.map(str::to_string).collect();
+
+    let result = match command.as_str() {
+        "help" | "start" => Ok(HELP.to_string()),
+        "version" => Ok(version()),
+        "yesterday" => yesterday(db).await,
+        "today" => today(db).await,
+        "month" => month(db).await,
+        "year" => year(db).await,
+        "stats" => run_stats(db, args).await,
+        "export" => return Some(run_export(db, args).await.unwrap_or_else(failed)),
+        "budgets" => budgets(db).await,
+        "costs" => costs(db).await,
+        "history" => history(db).await,
+        "schedule" => schedule(db, scheduler).await,
+        "status" => status(db, scheduler).await,
+        "baseload" => baseload(db).await,
+        "profile" => profile(db).await,
+        "devices" => devices(db).await,
+        "config" if !may_configure => {
+            info!("{} tried to change the config without permission.", author);
+            Ok("You're not allowed to change the config.".to_string())
+        }
+        "config" => run_config(db, scheduler, args, author).await,
+        _ => return None,
+    };
+    Some(result.map(Answer::from).unwrap_or_else(failed))
+}
+
+fn failed(err: anyhow::Error) -> Answer {
+    Answer::from(format!("Encountered an error: {}", err))
+}
+
+async fn run_export(db: &Db, args: Vec<String>) -> Result<Answer> {
+    let (mut format, mut hourly, mut dates) = (None, false, Vec::new());
+    for arg in args {
+        match arg.to_lowercase().as_str() {
+            "csv" => format = Some(Format::Csv),
+            "json" => format = Some(Format::Json),
+            "hourly" => hourly = true,
+            _ => dates.push(arg),
+        }
+    }
+    if dates.len() > 2 {
+        bail!("Usage: /export [csv|json] [hourly] [from] [to]");
+    }
+    let mut dates = dates.into_iter();
+    export(db, format, dates.next(), dates.next(), hourly).await
+}
+
+async fn run_stats(db: &Db, args: Vec<String>) -> Result<String> {
+    let mut args = args.into_iter().peekable();
+    let granularity = match args.peek().map(String::as_str) {
+        Some("day") => Some(Granularity::Day),
+        Some("week") => Some(Granularity::Week),
+        Some("month") => Some(Granularity::Month),
+        _ => None,
+    };
+    if granularity.is_some() {
+        args.next();
+    }
+    stats(db, granularity, args.next(), args.next()).await
+}
+
+async fn run_config(
+    db: &Db,
+    scheduler: &Scheduler,
+    args: Vec<String>,
+    author: &str,
+) -> Result<String> {
+    match args.first().map(String::as_str) {
+        Some("schedule") if args.len() > 1 => {
+            set_schedule(db, scheduler, &args[1..].join(" "), author).await
+        }
+        Some(kind) if args.len() >= 3 => {
+            let setting = Setting::parse(kind, &args[1])?;
+            set(db, setting, &args[2], args.get(3).cloned(), author).await
+        }
+        _ => Err(anyhow!(
+            "Please use /config cost|budget|fee heating|general <value> [valid from] or /config schedule <cron>."
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memory;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn set_rejects_invalid_values() {
+        let db: Db = Arc::new(Memory::new());
+        for value in ["NaN", "inf", "-inf", "-0.1", "abc", ""] {
+            let err = set(&db, Setting::CostHeating, value, None, "test")
+                .await
+                .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "The heating-cost has to be a number of at least 0 in €/kWh, not '{}'.",
+                    value
+                )
+            );
+        }
+        assert_eq!(db.get_config().await.unwrap().cost_heating, 0.23);
+    }
+
+    #[tokio::test]
+    async fn set_accepts_zero() {
+        let db: Db = Arc::new(Memory::new());
+        set(&db, Setting::BudgetGeneral, "0", None, "test")
+            .await
+            .unwrap();
+        assert_eq!(db.get_config().await.unwrap().monthly_budget_general, 0.0);
+    }
+}
@@ -1,49 +1,108 @@
-use crate::{meteo::Meteo, powerfox::{Powerfox, Report}};
+use crate::{memory::Memory, postgres::Postgres, powerfox::{self, Report}, util};
 use anyhow::{anyhow, bail, Result};
-use chrono::{Datelike, Local, NaiveDate, Duration};
-use log::info;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, DurationRound, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
 use serenity::utils::MessageBuilder;
-use sqlx::{
-    postgres::{PgConnectOptions, PgPoolOptions},
-    types::Uuid,
-    PgPool,
-};
-use std::env;
-
-#[derive(sqlx::FromRow)]
+use std::{collections::BTreeMap, env, sync::Arc};
+
+/// A version of the config, valid from [valid_from](Config::valid_from) until the next version.
+#[derive(sqlx::FromRow, Serialize, Clone)]
 pub struct Config {
-    pub id: Uuid,
+    pub valid_from: NaiveDate,
     pub cost_heating: f64,
     pub cost_general: f64,
     pub monthly_budget_heating: f64,
     pub monthly_budget_general: f64,
+    /// The monthly base fee of the heating-meter, prorated per day.
+    pub base_fee_heating: f64,
+    /// The monthly base fee of the general-meter, prorated per day.
+    pub base_fee_general: f64,
 }
 
 impl Config {
 
+    /// Create a new [Config] that is valid from the given date.
+    pub fn with_valid_from(self, valid_from: NaiveDate) -> Self {
+        Config { valid_from, ..self }
+    }
+
     /// Create a new [Config] with an updated value for [cost_heating].
     pub fn with_cost_heating(self, cost_heating: f64) -> Self {
-        Config { id: self.id, cost_heating, cost_general: self.cost_general, monthly_budget_heating: self.monthly_budget_heating, monthly_budget_general: self.monthly_budget_general }
+        Config { cost_heating, ..self }
     }
 
     /// Create a new [Config] with an updated value for [cost_general].
     pub fn with_cost_general(self, cost_general: f64) -> Self {
-        Config { id: self.id, cost_heating: self.cost_heating, cost_general, monthly_budget_heating: self.monthly_budget_heating, monthly_budget_general: self.monthly_budget_general }
+        Config { cost_general, ..self }
     }
 
     /// Create a new [Config] with an updated value for [monthly_budget_heating].
     pub fn with_monthly_budget_heating(self, monthly_budget_heating: f64) -> Self {
-        Config { id: self.id, cost_heating: self.cost_heating, cost_general: self.cost_general, monthly_budget_heating, monthly_budget_general: self.monthly_budget_general }
+        Config { monthly_budget_heating, ..self }
     }
     /// Create a new [Config] with an updated value for [monthly_budget_general].
     pub fn with_monthly_budget_general(self, monthly_budget_general: f64) -> Self {
-        Config { id: self.id, cost_heating: self.cost_heating, cost_general: self.cost_general, monthly_budget_heating: self.monthly_budget_heating, monthly_budget_general }
+        Config { monthly_budget_general, ..self }
+    }
+
+    /// Create a new [Config] with an updated value for [base_fee_heating].
+    pub fn with_base_fee_heating(self, base_fee_heating: f64) -> Self {
+        Config { base_fee_heating, ..self }
+    }
+
+    /// Create a new [Config] with an updated value for [base_fee_general].
+    pub fn with_base_fee_general(self, base_fee_general: f64) -> Self {
+        Config { base_fee_general, ..self }
     }
 }
 
-#[derive(sqlx::FromRow, Debug)]
+/// All versions of the config, ordered by the date they are valid from.
+pub struct ConfigHistory(pub Vec<Config>);
+
+impl ConfigHistory {
+    /// Get the config valid at the given date. Dates before the first version use the first version.
+    pub fn at(&self, date: NaiveDate) -> Result<&Config> {
+        self.0
+            .iter()
+            .rev()
+            .find(|config| config.valid_from <= date)
+            .or(self.0.first())
+            .ok_or(anyhow!("There is no config."))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Config> {
+        self.0.iter()
+    }
+
+    /// Get the config valid today.
+    pub fn current(&self) -> Result<&Config> {
+        self.at(util::today())
+    }
+
+    /// Get the sum of the monthly heating-budgets valid at the start of each month of the given year.
+    pub fn yearly_budget_heating(&self, year: i32) -> Result<f64> {
+        self.yearly_budget(year, |config| config.monthly_budget_heating)
+    }
+
+    /// Get the sum of the monthly general-budgets valid at the start of each month of the given year.
+    pub fn yearly_budget_general(&self, year: i32) -> Result<f64> {
+        self.yearly_budget(year, |config| config.monthly_budget_general)
+    }
+
+    fn yearly_budget(&self, year: i32, budget: impl Fn(&Config) -> f64) -> Result<f64> {
+        let mut sum = 0.0;
+        for month in 1..=12 {
+            let first_of_month = NaiveDate::from_ymd_opt(year, month, 1)
+                .ok_or(anyhow!("Could not create date for month {}.", month))?;
+            sum += budget(self.at(first_of_month)?);
+        }
+        Ok(sum)
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
 pub struct Day {
-    id: Uuid,
     // TODO maybe have time-series here at some point and calculate average_temperature and such?
     pub heating_consumption: f64,
     pub general_consumption: f64,
@@ -58,20 +117,166 @@ pub struct CreateDay {
     pub date: NaiveDate,
 }
 
-pub struct Days(Vec<Day>);
+impl From<CreateDay> for Day {
+    fn from(day: CreateDay) -> Self {
+        Day {
+            heating_consumption: day.heating_consumption,
+            general_consumption: day.general_consumption,
+            average_temperature: day.average_temperature,
+            date: day.date,
+        }
+    }
+}
+
+pub struct Days(pub Vec<Day>);
+
+/// The consumption of both meters in an hour, saved alongside its [Day].
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct Hour {
+    /// The start of the hour.
+    pub timestamp: DateTime<Utc>,
+    /// The day this hour belongs to.
+    pub date: NaiveDate,
+    pub heating_consumption: f64,
+    pub general_consumption: f64,
+}
+
+/// The highest report-value of both meters on a day, see [ValueWrapper::max](powerfox::ValueWrapper::max).
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct Peak {
+    pub date: NaiveDate,
+    pub heating_max: f64,
+    pub general_max: f64,
+}
+
+/// The length of the periods for [Storage::aggregate].
+#[derive(poise::ChoiceParameter, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+}
+
+impl Granularity {
+    /// The name used by the databases to truncate dates.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+        }
+    }
+
+    /// Get the first day of the period containing the given date. Weeks start on monday.
+    pub fn period_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => date,
+            Granularity::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Granularity::Month => util::first_of_month(date),
+        }
+    }
+}
+
+/// The aggregated values of all days in a period, see [Storage::aggregate].
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct Aggregate {
+    /// The first day of the period.
+    pub period: NaiveDate,
+    /// The number of days with data in this period.
+    pub days: i64,
+    pub heating_sum: f64,
+    pub heating_avg: f64,
+    pub heating_min: f64,
+    pub heating_max: f64,
+    pub general_sum: f64,
+    pub general_avg: f64,
+    pub general_min: f64,
+    pub general_max: f64,
+    pub average_temperature: f64,
+    pub heating_cost: f64,
+    pub general_cost: f64,
+}
+
+/// A device linked to the Powerfox-account, see [powerfox::Device].
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct Device {
+    pub device_id: String,
+    pub name: String,
+    pub main_device: bool,
+}
+
+/// A run of a job like the daily job, see [Storage::start_job_run].
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct JobRun {
+    pub id: i64,
+    /// The name of the job, e.g. [DAILY](crate::scheduler::DAILY).
+    pub job: String,
+    /// What started the run, see [Trigger].
+    pub triggered_by: String,
+    pub started_at: DateTime<Utc>,
+    /// When the run finished, which is empty while it's running.
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Either `running`, `success` or `error`.
+    pub status: String,
+    pub error: Option<String>,
+    /// The comma-separated days that were written.
+    pub days: String,
+}
+
+/// What started a [JobRun].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger {
+    /// The job's schedule.
+    Schedule,
+    /// A request to the HTTP-API.
+    Http,
+    /// The command-line.
+    Cli,
+    /// A command on one of the frontends, e.g. `/yesterday`.
+    Chat,
+}
+
+impl Trigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Trigger::Schedule => "schedule",
+            Trigger::Http => "http",
+            Trigger::Cli => "cli",
+            Trigger::Chat => "chat",
+        }
+    }
+}
+
+impl JobRun {
+    /// The status of a run that finished, with or without an error.
+    pub fn status_of(error: &Option<String>) -> &'static str {
+        match error {
+            Some(_) => "error",
+            None => "success",
+        }
+    }
+
+    /// Joins the days with commas, like they are stored.
+    pub fn join_days(days: &[NaiveDate]) -> String {
+        days.iter()
+            .map(NaiveDate::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
 
 impl Day {
     pub fn new(heating_report: Report, general_report: Report, average_temperature: f64) -> Self {
         Day {
-            id: Uuid::new_v4(),
             heating_consumption: heating_report.consumption.sum,
             general_consumption: general_report.consumption.sum,
             average_temperature,
-            date: Local::now().date_naive(),
+            date: util::today(),
         }
     }
 
-    pub fn summary(&self, cost_heating: &f64) -> String {
+    pub fn summary(&self, config: &Config) -> String {
         MessageBuilder::new()
                 .push_line_safe("Done getting data - here's your daily summary:")
                 .push_quote("With a temperature of ")
@@ -79,34 +284,42 @@ impl Day {
                 .push(", you've used ")
                 .push_bold_safe(format!("{:.2} kWh", self.heating_consumption))
                 .push(" for heating - this cost ")
-                .push_bold_safe(format!("{:.2} €", self.heating_cost(cost_heating)))
+                .push_bold_safe(format!("{:.2} €", self.heating_cost(config)))
                 .push(".")
                 .build()
     }
 
-    pub fn heating_cost(&self, cost: &f64) -> f64 {
-        self.heating_consumption * cost
+    /// Calculate the heating-cost with the given config, including this day's share of the base fee.
+    pub fn heating_cost(&self, config: &Config) -> f64 {
+        self.heating_consumption * config.cost_heating + self.prorate(config.base_fee_heating)
     }
 
-    pub fn general_cost(&self, cost: &f64) -> f64 {
-        self.general_consumption * cost
+    /// Calculate the general-cost with the given config, including this day's share of the base fee.
+    pub fn general_cost(&self, config: &Config) -> f64 {
+        self.general_consumption * config.cost_general + self.prorate(config.base_fee_general)
     }
-}
 
-impl Days {
-    pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+    /// Get this day's share of the given monthly fee.
+    fn prorate(&self, monthly_fee: f64) -> f64 {
+        monthly_fee / util::days_in_month(self.date) as f64
     }
+}
 
-    pub fn summary(&self, config: &Config) -> Result<String> {
-        match self.heating_cost(&config.cost_heating) {
-            Ok(total_heating_cost) => {
+impl Days {
+    pub fn summary(&self, configs: &ConfigHistory) -> Result<String> {
+        let config = configs.current()?;
+        match (self.heating_cost(configs), self.general_cost(configs)) {
+            (Ok(total_heating_cost), Ok(total_general_cost)) => {
                 let message = MessageBuilder::new()
                     .push_quote("This month, you've used ")
                     .push_bold_safe(format!("{:.2} €", total_heating_cost))
                     .push(" of ")
                     .push_bold_safe(format!("{:.2} €", config.monthly_budget_heating))
-                    .push(".")
+                    .push(" for heating and ")
+                    .push_bold_safe(format!("{:.2} €", total_general_cost))
+                    .push(" of ")
+                    .push_bold_safe(format!("{:.2} €", config.monthly_budget_general))
+                    .push(" for general consumption.")
                     .build();
                 Ok(message)
             }
@@ -114,183 +327,243 @@ impl Days {
         }
     }
 
-    pub fn heating_cost(&self, cost: &f64) -> Result<f64> {
-        let consumption = self.0.iter().map(|d| d.heating_cost(cost)).reduce(|a, b| a + b);
-        match consumption {
-            Some(val) => Ok(val),
-            None => bail!("Could not calculate heating-consumption."),
+    /// Calculate the heating-cost including base fees, using the config valid on each day.
+    pub fn heating_cost(&self, configs: &ConfigHistory) -> Result<f64> {
+        self.cost(configs, Day::heating_cost)
+            .map_err(|_| anyhow!("Could not calculate heating-consumption."))
+    }
+
+    /// Calculate the general-cost including base fees, using the config valid on each day.
+    pub fn general_cost(&self, configs: &ConfigHistory) -> Result<f64> {
+        self.cost(configs, Day::general_cost)
+            .map_err(|_| anyhow!("Could not calculate general-consumption."))
+    }
+
+    fn cost(&self, configs: &ConfigHistory, cost_of: fn(&Day, &Config) -> f64) -> Result<f64> {
+        let mut cost = None;
+        for day in &self.0 {
+            let day_cost = cost_of(day, configs.at(day.date)?);
+            cost = Some(cost.unwrap_or(0.0) + day_cost);
         }
+        cost.ok_or(anyhow!("There are no days."))
     }
+}
 
-    pub fn general_cost(&self, cost: &f64) -> Result<f64> {
-        let consumption = self.0.iter().map(|d| d.general_cost(cost)).reduce(|a, b| a + b);
-        match consumption {
-            Some(val) => Ok(val),
-            None => bail!("Could not calculate general-consumption."),
+impl Hour {
+    /// Sum up the report-values of both meters per hour.
+    pub fn from_reports(date: NaiveDate, heating_report: &Report, general_report: &Report) -> Vec<Hour> {
+        let mut hours: BTreeMap<DateTime<Utc>, Hour> = BTreeMap::new();
+        for (report, is_heating) in [(heating_report, true), (general_report, false)] {
+            for value in &report.consumption.report_values {
+                let timestamp = value.timestamp.duration_trunc(Duration::hours(1)).unwrap_or(value.timestamp);
+                let hour = hours.entry(timestamp).or_insert(Hour {
+                    timestamp,
+                    date,
+                    heating_consumption: 0.0,
+                    general_consumption: 0.0,
+                });
+                if is_heating {
+                    hour.heating_consumption += value.delta;
+                } else {
+                    hour.general_consumption += value.delta;
+                }
+            }
+        }
+        hours.into_values().collect()
+    }
+}
+
+impl Peak {
+    pub fn from_reports(date: NaiveDate, heating_report: &Report, general_report: &Report) -> Self {
+        Peak {
+            date,
+            heating_max: heating_report.consumption.max,
+            general_max: general_report.consumption.max,
         }
     }
 }
 
 impl CreateDay {
-    pub fn yesterday(heating_report: Report, general_report: Report, average_temperature: f64) -> Self {
+    pub fn new(date: NaiveDate, heating_report: &Report, general_report: &Report, average_temperature: f64) -> Self {
         CreateDay {
             heating_consumption: heating_report.consumption.sum,
             general_consumption: general_report.consumption.sum,
             average_temperature,
-            date: Local::now().date_naive() - Duration::days(1),
+            date,
         }
     }
 }
 
-#[derive(Clone)]
-pub struct Db {
-    pool: PgPool,
+impl From<&powerfox::Device> for Device {
+    fn from(device: &powerfox::Device) -> Self {
+        Device {
+            device_id: device.device_id.clone(),
+            name: device.name.clone(),
+            main_device: device.main_device,
+        }
+    }
 }
 
+/// The storage used throughout the app, see [connect].
+pub type Db = Arc<dyn Storage>;
 
-impl Db {
-    pub async fn new() -> Result<Self> {
-        let username = env::var("DATABASE_USER")?;
-        let password = env::var("DATABASE_PASSWORD")?;
-        let host = env::var("DATABASE_HOST")?;
-        let port: u16 = env::var("DATABASE_PORT")?.parse()?;
-        let database = env::var("DATABASE_TABLE")?;
-
-        let options = PgConnectOptions::new()
-            .host(&host)
-            .port(port)
-            .username(&username)
-            .password(&password)
-            .database(&database);
-
-        let pool = PgPoolOptions::new()
-            .max_connections(5) 
-            .connect_with(options)
-            .await?;
-
-        info!("Set up database-client.");
-        Ok(Db {pool})
-    }
-
-    /// Updates the event if it exists, otherwise just adds it to the database.
-    pub async fn save_yesterday(&self, day: CreateDay) -> Result<Day> {
-        match self.get_yesterday().await {
-            Ok(existing_day) => {
-                // we already have a day, no need to create another
-                self.update_day(existing_day.id, day).await
-            },
-            Err(_) => {
-                let day = sqlx::query_as!(Day, 
-                    "INSERT INTO days (id, heating_consumption, general_consumption, average_temperature, date) VALUES ($1, $2, $3, $4, $5) RETURNING id, heating_consumption, general_consumption, average_temperature, date",
-                    Uuid::new_v4(), day.heating_consumption, day.general_consumption, day.average_temperature, day.date).fetch_one(&self.pool).await?;
-                Ok(day)
-            },
-        }
-    }
+/// Connects to the storage selected by `DATABASE_BACKEND`: `postgres` (the default), `sqlite` or `memory`.
+/// `sqlite` is only available with the `sqlite`-feature. Pending migrations are applied.
+pub async fn connect() -> Result<Db> {
+    open(false).await
+}
 
-    pub async fn update_day(&self, id: Uuid, day: CreateDay) -> Result<Day> {
-        let day = sqlx::query_as!(Day, 
-            "UPDATE days SET (heating_consumption, general_consumption, average_temperature, date) = ($1, $2, $3, $4) WHERE id = $5 RETURNING id, heating_consumption, general_consumption, average_temperature, date",
-            day.heating_consumption, day.general_consumption, day.average_temperature, day.date, id).fetch_one(&self.pool).await?;
-        Ok(day)
-    }
+/// Connects like [connect], but neither migrates nor allows writes, e.g. for dry runs.
+pub async fn connect_read_only() -> Result<Db> {
+    open(true).await
+}
 
-    /// Get a specific day from the database.
-    pub async fn get_day(&self, date: NaiveDate) -> Result<Day> {
-        let day = sqlx::query_as!(Day, "SELECT * FROM days WHERE date = $1", date)
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(day)
+async fn open(read_only: bool) -> Result<Db> {
+    match env::var("DATABASE_BACKEND").as_deref() {
+        Ok("postgres") | Err(_) => Ok(Arc::new(Postgres::new(read_only).await?)),
+        #[cfg(feature = "sqlite")]
+        Ok("sqlite") => Ok(Arc::new(crate::sqlite::Sqlite::new(read_only).await?)),
+        #[cfg(not(feature = "sqlite"))]
+        Ok("sqlite") => bail!("DATABASE_BACKEND 'sqlite' requires building with the 'sqlite'-feature."),
+        Ok("memory") => Ok(Arc::new(Memory::new())),
+        Ok(backend) => bail!("Unknown DATABASE_BACKEND '{}'.", backend),
     }
+}
 
-    /// Wrapper around [get_day](Db::get_day).
-    pub async fn get_yesterday(&self) -> Result<Day> {
-        let yesterday = Local::now().date_naive() - Duration::days(1);
-        self.get_day(yesterday).await
-    }
+/// Stores days, hours, peaks, config, schedules and devices.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Saves the days, replacing existing days with the same date - either all or none of them.
+    async fn save_days(&self, days: Vec<CreateDay>) -> Result<()>;
 
-    /// Create the entry for yesterday. Loads from the database if it exists already
-    pub async fn create_yesterday(&self) -> Result<Day> {
-            // if we have the data already, just return it to save on API-calls
-    if let Ok(day) = self.get_yesterday().await {
-        return Ok(day);
-    }
+    /// Saves a day fetched from Powerfox with its hours and peak, replacing existing ones - either all or none of them.
+    async fn save_import(&self, day: CreateDay, hours: Vec<Hour>, peak: Peak) -> Result<Day>;
 
-    let meteo = Meteo::new()?;
-    let temperature = meteo.get_temperature_for_yesterday().await?;
+    /// Get a specific day, if it exists.
+    async fn get_day(&self, date: NaiveDate) -> Result<Option<Day>>;
 
-    let powerfox = Powerfox::new()?;
-    let devices = powerfox.get_devices().await?;
+    /// Get the most recent day, if there is any.
+    async fn get_latest_day(&self) -> Result<Option<Day>>;
 
-    let mut heating_report = None;
-    let mut general_report = None;
-    for device in devices {
-        if device.name == "Heizstrom" {
-            heating_report = Some(powerfox.get_report_for_yesterday(&device.device_id).await?);
-        }
+    /// Get all days between both dates, including both.
+    async fn get_days_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Days>;
 
-        if device.name == "Allgemeinstrom" {
-            general_report = Some(powerfox.get_report_for_yesterday(&device.device_id).await?);
-        }
+    /// Get the hours of all days between both dates, including both.
+    async fn get_hours_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Hour>>;
 
-        // checking this in the loop might be beneficial if there are multiple other devices
-        if heating_report.is_some() && general_report.is_some() {
-            let yesterday = CreateDay::yesterday(
-                // we can just unwrap here because we check with is_some() before
-                heating_report.unwrap(),
-                general_report.unwrap(),
-                temperature.average_temperature()?,
-            );
+    /// Get the peaks of all days between both dates, including both.
+    async fn get_peaks_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Peak>>;
 
-            return self.save_yesterday(yesterday).await;
-        }
+    /// Aggregate the days between both dates (including both) per period of the given granularity.
+    /// Costs use the config valid on each day, like [Days::heating_cost] and [Days::general_cost].
+    async fn aggregate(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        granularity: Granularity,
+    ) -> Result<Vec<Aggregate>>;
+
+    /// Get all versions of the config.
+    async fn get_config_history(&self) -> Result<ConfigHistory>;
+
+    /// Save the config as the version valid from the given date, replacing an existing version for that date.
+    async fn save_config(&self, config: Config) -> Result<Config>;
+
+    /// Get the stored cron-schedule of the job with the given name, if it was ever changed.
+    /// See [Scheduler](crate::scheduler::Scheduler).
+    async fn get_schedule(&self, name: &str) -> Result<Option<String>>;
+
+    /// Save the cron-schedule of the job with the given name, replacing the previous one.
+    async fn save_schedule(&self, name: &str, cron: &str) -> Result<()>;
+
+    /// Get all known devices.
+    async fn get_devices(&self) -> Result<Vec<Device>>;
+
+    /// Save the devices, replacing existing devices with the same ID.
+    async fn save_devices(&self, devices: Vec<Device>) -> Result<()>;
+
+    /// Save the start of a run of the given job, returning its ID for [Storage::finish_job_run].
+    async fn start_job_run(&self, job: &str, trigger: Trigger) -> Result<i64>;
+
+    /// Save the end of the run, which failed if there's an error, and the days it wrote.
+    async fn finish_job_run(&self, id: i64, error: Option<String>, days: Vec<NaiveDate>) -> Result<()>;
+
+    /// Get the most recent runs of all jobs, the latest first.
+    async fn get_job_runs(&self, limit: i64) -> Result<Vec<JobRun>>;
+
+    /// Get the config valid today.
+    async fn get_config(&self) -> Result<Config> {
+        self.get_config_at(util::today()).await
     }
 
-    Err(anyhow!(
-        "Could not get all necessary data for the current day."
-    ))
+    /// Get the config valid at the given date, see [ConfigHistory::at].
+    async fn get_config_at(&self, date: NaiveDate) -> Result<Config> {
+        Ok(self.get_config_history().await?.at(date)?.clone())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    /// Get all days of the current month.
-    pub async fn get_days_of_month(&self) -> Result<Days> {
-        let current_date = Local::now().naive_local();
-        let first_of_month = NaiveDate::from_ymd_opt(current_date.year(), current_date.month(), 1)
-            .ok_or(anyhow!("Could not create date for this month."))?;
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
 
-        let days = sqlx::query_as!(Day, "SELECT * FROM days WHERE date > $1", first_of_month)
-            .fetch_all(&self.pool)
-            .await?;
+    fn day(date: NaiveDate, heating_consumption: f64, general_consumption: f64) -> Day {
+        Day { heating_consumption, general_consumption, average_temperature: 10.0, date }
+    }
 
-        Ok(Days(days))
+    /// The default config of a fresh [Memory] with a cheaper, larger budget from July 2026 on.
+    async fn history() -> ConfigHistory {
+        let db = Memory::new();
+        let default = db.get_config_at(date(2026, 1, 1)).await.unwrap();
+        db.save_config(Config { monthly_budget_heating: 200.0, monthly_budget_general: 80.0, ..default.with_valid_from(date(2026, 7, 1)) }).await.unwrap();
+        db.get_config_history().await.unwrap()
     }
 
-    /// Get all days of the current year.
-    pub async fn get_days_of_year(&self) -> Result<Days> {
-        let current_date = Local::now().naive_local();
-        let first_of_year = NaiveDate::from_ymd_opt(current_date.year(), 1, 1)
-            .ok_or(anyhow!("Could not create date for this month."))?;
+    #[tokio::test]
+    async fn config_at_uses_the_latest_version_before_the_date() {
+        let configs = history().await;
+        assert_eq!(configs.at(date(2026, 6, 30)).unwrap().monthly_budget_heating, 460.0);
+        assert_eq!(configs.at(date(2026, 7, 1)).unwrap().monthly_budget_heating, 200.0);
+        assert_eq!(configs.at(date(2030, 1, 1)).unwrap().monthly_budget_heating, 200.0);
+    }
 
-        let days = sqlx::query_as!(Day, "SELECT * FROM days WHERE date > $1", first_of_year)
-            .fetch_all(&self.pool)
-            .await?;
+    #[tokio::test]
+    async fn config_at_falls_back_to_the_first_version() {
+        let configs = history().await;
+        let later = ConfigHistory(configs.0[1..].to_vec());
+        assert_eq!(later.at(date(2026, 1, 1)).unwrap().valid_from, date(2026, 7, 1));
+        assert!(ConfigHistory(Vec::new()).at(date(2026, 1, 1)).is_err());
+    }
 
-        Ok(Days(days))
+    #[tokio::test]
+    async fn yearly_budget_sums_the_budget_of_each_month() {
+        let configs = history().await;
+        assert_eq!(configs.yearly_budget_heating(2025).unwrap(), 12.0 * 460.0);
+        assert_eq!(configs.yearly_budget_heating(2026).unwrap(), 6.0 * 460.0 + 6.0 * 200.0);
+        assert_eq!(configs.yearly_budget_general(2026).unwrap(), 6.0 * 50.0 + 6.0 * 80.0);
     }
 
-    /// Get the current config from the database.
-    pub async fn get_config(&self) -> Result<Config> {
-        let config = sqlx::query_as!(Config, "SELECT * FROM config")
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(config)
+    #[tokio::test]
+    async fn costs_include_the_prorated_base_fee() {
+        let config = Config { cost_heating: 0.25, cost_general: 0.5, base_fee_heating: 28.0, base_fee_general: 14.0, ..history().await.at(date(2026, 1, 1)).unwrap().clone() };
+        // February 2026 has 28 days
+        let february = day(date(2026, 2, 10), 10.0, 4.0);
+        assert_eq!(february.heating_cost(&config), 2.5 + 1.0);
+        assert_eq!(february.general_cost(&config), 2.0 + 0.5);
+        // March has 31 days, so each day gets a smaller share
+        let march = day(date(2026, 3, 10), 10.0, 4.0);
+        assert_eq!(march.heating_cost(&config), 2.5 + 28.0 / 31.0);
     }
 
-    /// Update the existing config.
-    pub async fn update_config(&self, id: Uuid, config: Config) -> Result<Config> {
-        let config = sqlx::query_as!(Config, 
-            "UPDATE config SET (cost_heating, cost_general, monthly_budget_heating, monthly_budget_general) = ($1, $2, $3, $4) WHERE id = $5 RETURNING id, cost_heating, cost_general, monthly_budget_heating, monthly_budget_general", config.cost_heating, config.cost_general, config.monthly_budget_heating, config.monthly_budget_general, id)
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(config)
+    #[tokio::test]
+    async fn days_of_a_month_add_up_to_the_whole_base_fee() {
+        let configs = ConfigHistory(vec![Config { cost_heating: 0.0, cost_general: 0.0, base_fee_heating: 30.0, base_fee_general: 12.0, ..history().await.at(date(2026, 1, 1)).unwrap().clone() }]);
+        let april = Days((1..=30).map(|april| day(date(2026, 4, april), 1.0, 1.0)).collect());
+        assert!((april.heating_cost(&configs).unwrap() - 30.0).abs() < 1e-9);
+        assert!((april.general_cost(&configs).unwrap() - 12.0).abs() < 1e-9);
+        assert!(Days(Vec::new()).heating_cost(&configs).is_err());
     }
 }
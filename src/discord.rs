@@ -1,27 +1,51 @@
-use ::serenity::all::Http;
-use anyhow::Result;
-use log::info;
+use ::serenity::all::{ConnectionStage, Http, ShardManager};
+use anyhow::{bail, Result};
+use log::warn;
 use poise::{samples::HelpConfiguration, serenity_prelude as serenity};
 use serenity::model::prelude::*;
-use std::env;
+use std::{
+    collections::HashSet,
+    env,
+    sync::{Arc, OnceLock},
+};
 
 use crate::{
-    db::{Day, Db},
-    meteo::Meteo,
-    powerfox::Powerfox,
+    commands::{self, Setting},
+    db::{Db, Granularity},
+    export::Format,
+    scheduler::Scheduler,
 };
 
-pub async fn start_bot(token: &str, intents: GatewayIntents, db: Db) -> Result<()> {
+/// The shards of the running bot, see [gateway].
+static SHARDS: OnceLock<Arc<ShardManager>> = OnceLock::new();
+
+pub async fn start_bot(
+    token: String,
+    intents: GatewayIntents,
+    db: Db,
+    scheduler: Scheduler,
+) -> Result<()> {
+    let permissions = ConfigPermissions::from_env()?;
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
+            // the bot's owner is added to these automatically
+            owners: permissions.owners.clone(),
             commands: vec![
                 version(),
                 yesterday(),
                 today(),
                 month(),
                 year(),
+                stats(),
+                export(),
                 budgets(),
                 costs(),
+                history(),
+                schedule(),
+                status(),
+                baseload(),
+                profile(),
+                devices(),
                 help(),
                 config(),
             ],
@@ -30,19 +54,48 @@ pub async fn start_bot(token: &str, intents: GatewayIntents, db: Db) -> Result<(
         .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                Ok(Data { db })
+                Ok(Data {
+                    db,
+                    scheduler,
+                    permissions,
+                })
             })
         })
         .build();
 
-    let mut client = serenity::ClientBuilder::new(token, intents)
+    let mut client = serenity::ClientBuilder::new(&token, intents)
         .framework(framework)
         .await?;
 
+    SHARDS.set(client.shard_manager.clone()).ok();
     client.start().await?;
     Ok(())
 }
 
+/// The state of the bot's connection to Discord's gateway, failing unless every shard is connected.
+pub async fn gateway() -> Result<String> {
+    let Some(shards) = SHARDS.get() else {
+        bail!("the bot hasn't started yet");
+    };
+    let runners = shards.runners.lock().await;
+    if runners.is_empty() {
+        bail!("no shard is running");
+    }
+    let mut latencies = Vec::new();
+    for (id, runner) in runners.iter() {
+        if !matches!(runner.stage, ConnectionStage::Connected) {
+            bail!("shard {} is {}", id, runner.stage);
+        }
+        if let Some(latency) = runner.latency {
+            latencies.push(latency.as_millis().to_string());
+        }
+    }
+    if latencies.is_empty() {
+        return Ok("connected".to_string());
+    }
+    Ok(format!("connected, latency {} ms", latencies.join(", ")))
+}
+
 pub async fn say(token: &str, channel_id: ChannelId, content: impl Into<String>) -> Result<()> {
     let cache_http = Http::new(token);
     channel_id.say(cache_http, content).await?;
@@ -51,18 +104,107 @@ pub async fn say(token: &str, channel_id: ChannelId, content: impl Into<String>)
 
 pub struct Data {
     db: Db,
+    scheduler: Scheduler,
+    permissions: ConfigPermissions,
 }
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
 
+/// Who is allowed to use commands that change the config, see [can_configure].
+pub struct ConfigPermissions {
+    /// Users from `DISCORD_OWNERS`, a comma-separated list of user-IDs.
+    owners: HashSet<UserId>,
+    /// The role from `DISCORD_CONFIG_ROLE`, if set.
+    role: Option<RoleId>,
+    /// Whether administrators of the guild are allowed, read from `DISCORD_ALLOW_ADMINS` and defaulting to true.
+    allow_admins: bool,
+}
+
+impl ConfigPermissions {
+    pub fn from_env() -> Result<Self> {
+        let mut owners = HashSet::new();
+        if let Ok(ids) = env::var("DISCORD_OWNERS") {
+            for id in ids.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+                owners.insert(UserId::new(id.parse()?));
+            }
+        }
+
+        let role = match env::var("DISCORD_CONFIG_ROLE") {
+            Ok(id) => Some(RoleId::new(id.parse()?)),
+            Err(_) => None,
+        };
+
+        let allow_admins = match env::var("DISCORD_ALLOW_ADMINS") {
+            Ok(allow) => allow.parse()?,
+            Err(_) => true,
+        };
+
+        Ok(ConfigPermissions {
+            owners,
+            role,
+            allow_admins,
+        })
+    }
+}
+
+/// Checks whether the author is allowed to change the config, see [ConfigPermissions].
+/// Owners are always allowed, everyone else needs the configured role or to be an administrator of the guild.
+async fn can_configure(ctx: Context<'_>) -> Result<bool, Error> {
+    let permissions = &ctx.data().permissions;
+    let author = ctx.author();
+
+    if ctx.framework().options().owners.contains(&author.id) {
+        return Ok(true);
+    }
+
+    if let (Some(guild_id), Some(member)) = (ctx.guild_id(), ctx.author_member().await) {
+        if permissions
+            .role
+            .is_some_and(|role| member.roles.contains(&role))
+        {
+            return Ok(true);
+        }
+
+        if permissions.allow_admins && is_admin(ctx, guild_id, &member).await? {
+            return Ok(true);
+        }
+    }
+
+    warn!(
+        "{} ({}) tried to change the config without permission.",
+        author.name, author.id
+    );
+    ctx.say("You're not allowed to change the config.").await?;
+    Ok(false)
+}
+
+/// Whether the member owns the guild or has the administrator-permission.
+async fn is_admin(ctx: Context<'_>, guild_id: GuildId, member: &Member) -> Result<bool, Error> {
+    // permissions are only included for slash-commands, so we need to resolve the roles for prefix-commands
+    if let Some(permissions) = member.permissions {
+        return Ok(permissions.administrator());
+    }
+
+    let guild = guild_id.to_partial_guild(ctx.http()).await?;
+    let is_admin = guild.owner_id == member.user.id
+        || member
+            .roles
+            .iter()
+            .chain(std::iter::once(&guild_id.everyone_role()))
+            .filter_map(|id| guild.roles.get(id))
+            .any(|role| role.permissions.administrator());
+    Ok(is_admin)
+}
+
+/// The author as logged by [commands::set].
+fn author(ctx: Context<'_>) -> String {
+    format!("{} ({})", ctx.author().name, ctx.author().id)
+}
+
 /// Display the bot's version.
 #[poise::command(slash_command, prefix_command)]
 async fn version(ctx: Context<'_>) -> Result<(), Error> {
-    ctx.say(format!(
-        "The bot-version is {}.",
-        env::var("CARGO_PKG_VERSION").unwrap_or("<could not find version>".to_string())
-    ))
-    .await?;
+    ctx.say(commands::version()).await?;
     Ok(())
 }
 
@@ -71,11 +213,7 @@ async fn version(ctx: Context<'_>) -> Result<(), Error> {
 async fn yesterday(ctx: Context<'_>) -> Result<(), Error> {
     // see today() for why we'd better send an initial message here
     ctx.say("Computing data for yesterday.").await?;
-
-    let yesterday = ctx.data().db.create_yesterday().await?;
-    let config = ctx.data().db.get_config().await?;
-    ctx.say(format!("{}", yesterday.summary(&config.cost_heating)))
-        .await?;
+    ctx.say(commands::yesterday(&ctx.data().db).await?).await?;
     Ok(())
 }
 
@@ -85,64 +223,66 @@ async fn today(ctx: Context<'_>) -> Result<(), Error> {
     // There needs to be a response in the first 3 seconds or Discord treats this as an error.
     // Following messages are still sent.
     ctx.say("Computing data for today.").await?;
-    let config = ctx.data().db.get_config().await?;
-    let meteo = Meteo::new()?;
-    let temperature = meteo.get_temperature_for_today().await?;
-
-    let powerfox = Powerfox::new()?;
-    let devices = powerfox.get_devices().await?;
+    ctx.say(commands::today(&ctx.data().db).await?).await?;
+    Ok(())
+}
 
-    let mut heating_report = None;
-    let mut general_report = None;
+/// Display the configured budgets.
+#[poise::command(slash_command, prefix_command)]
+async fn budgets(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say(commands::budgets(&ctx.data().db).await?).await?;
+    Ok(())
+}
+/// Display the configured costs.
+#[poise::command(slash_command, prefix_command)]
+async fn costs(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say(commands::costs(&ctx.data().db).await?).await?;
+    Ok(())
+}
 
-    // TODO this works, but it might be nice to have this unified somewhere
-    for device in devices {
-        if device.name == "Heizstrom" {
-            heating_report = Some(powerfox.get_report_for_today(&device.device_id).await?);
-        }
+/// Display all versions of the config.
+#[poise::command(slash_command, prefix_command)]
+async fn history(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say(commands::history(&ctx.data().db).await?).await?;
+    Ok(())
+}
 
-        if device.name == "Allgemeinstrom" {
-            general_report = Some(powerfox.get_report_for_today(&device.device_id).await?);
-        }
-    }
+/// Display the schedule of the daily summary.
+#[poise::command(slash_command, prefix_command)]
+async fn schedule(ctx: Context<'_>) -> Result<(), Error> {
+    let data = ctx.data();
+    ctx.say(commands::schedule(&data.db, &data.scheduler).await?)
+        .await?;
+    Ok(())
+}
 
-    if heating_report.is_some() && general_report.is_some() {
-        // NOTE we can't save this as the day isn't over yet
-        let day = Day::new(
-            heating_report.unwrap(),
-            general_report.unwrap(),
-            temperature.average_temperature()?,
-        );
-        ctx.say(day.summary(&config.cost_heating)).await?;
-    } else {
-        ctx.say("Something went wrong.").await?;
-    }
+/// Display the recent runs of the jobs and the next daily run.
+#[poise::command(slash_command, prefix_command)]
+async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    let data = ctx.data();
+    ctx.say(commands::status(&data.db, &data.scheduler).await?)
+        .await?;
+    Ok(())
+}
 
-    // because this takes some time, this log is used to check if the task is completed
-    info!("Done with /today");
+/// Display the consumption of always-on devices and its yearly cost.
+#[poise::command(slash_command, prefix_command)]
+async fn baseload(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say(commands::baseload(&ctx.data().db).await?).await?;
     Ok(())
 }
 
-/// Display the configured budgets.
+/// Display the average consumption per hour of each weekday and the daily peaks.
 #[poise::command(slash_command, prefix_command)]
-async fn budgets(ctx: Context<'_>) -> Result<(), Error> {
-    let config = ctx.data().db.get_config().await?;
-    ctx.say(format!(
-        "Heating-Budget: {}€\nGeneral Budget: {}€",
-        config.monthly_budget_heating, config.monthly_budget_general
-    ))
-    .await?;
+async fn profile(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say(commands::profile(&ctx.data().db).await?).await?;
     Ok(())
 }
-/// Display the configured costs.
+
+/// Display the devices linked to the Powerfox-account.
 #[poise::command(slash_command, prefix_command)]
-async fn costs(ctx: Context<'_>) -> Result<(), Error> {
-    let config = ctx.data().db.get_config().await?;
-    ctx.say(format!(
-        "Heating-Cost: {}€\nGeneral Cost: {}€",
-        config.cost_heating, config.cost_general
-    ))
-    .await?;
+async fn devices(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say(commands::devices(&ctx.data().db).await?).await?;
     Ok(())
 }
 
@@ -151,23 +291,7 @@ async fn costs(ctx: Context<'_>) -> Result<(), Error> {
 async fn month(ctx: Context<'_>) -> Result<(), Error> {
     // see today() for why we'd better send an initial message here
     ctx.say("Computing data for the current month.").await?;
-
-    let config = ctx.data().db.get_config().await?;
-    let days = ctx.data().db.get_days_of_month().await?;
-
-    if days.is_empty() {
-        ctx.say("No data for the current month.").await?;
-    } else {
-        ctx.say(format!(
-            "Cost of Heating: {:.2}€/{}€\nGeneral cost: {:.2}€/{}€",
-            days.heating_cost(&config.cost_heating)?,
-            config.monthly_budget_heating,
-            days.general_cost(&config.cost_general)?,
-            config.monthly_budget_general
-        ))
-        .await?;
-    }
-
+    ctx.say(commands::month(&ctx.data().db).await?).await?;
     Ok(())
 }
 
@@ -176,28 +300,57 @@ async fn month(ctx: Context<'_>) -> Result<(), Error> {
 async fn year(ctx: Context<'_>) -> Result<(), Error> {
     // see today() for why we'd better send an initial message here
     ctx.say("Computing data for the current year.").await?;
+    ctx.say(commands::year(&ctx.data().db).await?).await?;
+    Ok(())
+}
 
-    let config = ctx.data().db.get_config().await?;
-    let days = ctx.data().db.get_days_of_year().await?;
-
-    if days.is_empty() {
-        ctx.say("No data for the current year.").await?;
-    } else {
-        ctx.say(format!(
-            "Cost of Heating: {:.2}€/{}€\nGeneral cost: {:.2}€/{}€",
-            days.heating_cost(&config.cost_heating)?,
-            config.monthly_budget_heating * 12.0,
-            days.general_cost(&config.cost_general)?,
-            config.monthly_budget_general * 12.0
-        ))
+/// Display consumption-statistics per day, week or month. Defaults to months of the current year.
+#[poise::command(slash_command, prefix_command)]
+async fn stats(
+    ctx: Context<'_>,
+    #[description = "Length of the periods, defaults to month"] granularity: Option<Granularity>,
+    #[description = "First date (YYYY-MM-DD), defaults to the start of the year"] from: Option<
+        String,
+    >,
+    #[description = "Last date (YYYY-MM-DD), defaults to today"] to: Option<String>,
+) -> Result<(), Error> {
+    ctx.say(commands::stats(&ctx.data().db, granularity, from, to).await?)
         .await?;
-    }
+    Ok(())
+}
 
+/// Export the stored days as a file. Defaults to a CSV of the current month.
+#[poise::command(slash_command, prefix_command, rename = "export")]
+async fn export(
+    ctx: Context<'_>,
+    #[description = "File-format, defaults to CSV"] format: Option<Format>,
+    #[description = "First date (YYYY-MM-DD), defaults to the start of the month"] from: Option<
+        String,
+    >,
+    #[description = "Last date (YYYY-MM-DD), defaults to today"] to: Option<String>,
+    #[description = "Include hourly values - as CSV, this exports one row per hour"] hourly: Option<
+        bool,
+    >,
+) -> Result<(), Error> {
+    let answer =
+        commands::export(&ctx.data().db, format, from, to, hourly.unwrap_or(false)).await?;
+    let mut reply = poise::CreateReply::default().content(answer.text);
+    if let Some(file) = answer.file {
+        reply = reply.attachment(serenity::CreateAttachment::bytes(file.data, file.name));
+    }
+    ctx.send(reply).await?;
     Ok(())
 }
 
-/// Update config-values. For viewing values, see /costs and /budget.
-#[poise::command(prefix_command, slash_command, subcommands("cost", "budget"))]
+/// Update config-values. For viewing values, see /costs, /budget and /schedule.
+///
+/// The check also applies to all subcommands.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("cost", "budget", "fee", "config_schedule"),
+    check = "can_configure"
+)]
 pub async fn config(ctx: Context<'_>, _arg: String) -> Result<(), Error> {
     ctx.say("Please call this command with a subcommand.")
         .await?;
@@ -228,53 +381,118 @@ pub async fn budget(ctx: Context<'_>, _arg: String) -> Result<(), Error> {
     Ok(())
 }
 
-/// Update the heating-cost.
-#[poise::command(prefix_command, slash_command, rename = "heating")]
-pub async fn cost_heating(ctx: Context<'_>, cost_heating: String) -> Result<(), Error> {
-    let mut config = ctx.data().db.get_config().await?;
-    config = config.with_cost_heating(cost_heating.parse::<f64>()?);
-    config = ctx.data().db.update_config(config.id, config).await?;
-    ctx.say(format!("Updated heating-cost to {}€.", config.cost_heating))
+/// Update base-fee-config-values. For viewing base fees, see /costs.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("fee_heating", "fee_general")
+)]
+pub async fn fee(ctx: Context<'_>, _arg: String) -> Result<(), Error> {
+    ctx.say("Please call this command with a subcommand.")
         .await?;
     Ok(())
 }
 
+/// Update a config-value and answer with the result.
+async fn set(
+    ctx: Context<'_>,
+    setting: Setting,
+    value: String,
+    valid_from: Option<String>,
+) -> Result<(), Error> {
+    let message = commands::set(&ctx.data().db, setting, &value, valid_from, &author(ctx)).await?;
+    ctx.say(message).await?;
+    Ok(())
+}
+
+/// Update the heating-cost.
+#[poise::command(prefix_command, slash_command, rename = "heating")]
+pub async fn cost_heating(
+    ctx: Context<'_>,
+    cost_heating: String,
+    #[description = "Date from which the value applies (YYYY-MM-DD), defaults to today"]
+    valid_from: Option<String>,
+) -> Result<(), Error> {
+    set(ctx, Setting::CostHeating, cost_heating, valid_from).await
+}
+
 /// Update the general-cost.
 #[poise::command(prefix_command, slash_command, rename = "general")]
-pub async fn cost_general(ctx: Context<'_>, cost_general: String) -> Result<(), Error> {
-    let mut config = ctx.data().db.get_config().await?;
-    config = config.with_cost_general(cost_general.parse::<f64>()?);
-    config = ctx.data().db.update_config(config.id, config).await?;
-    ctx.say(format!("Updated general-cost to {}€.", config.cost_general))
-        .await?;
-    Ok(())
+pub async fn cost_general(
+    ctx: Context<'_>,
+    cost_general: String,
+    #[description = "Date from which the value applies (YYYY-MM-DD), defaults to today"]
+    valid_from: Option<String>,
+) -> Result<(), Error> {
+    set(ctx, Setting::CostGeneral, cost_general, valid_from).await
 }
 
 /// Update the heating-budget.
 #[poise::command(prefix_command, slash_command, rename = "heating")]
-pub async fn budget_heating(ctx: Context<'_>, monthly_budget_heating: String) -> Result<(), Error> {
-    let mut config = ctx.data().db.get_config().await?;
-    config = config.with_monthly_budget_heating(monthly_budget_heating.parse::<f64>()?);
-    config = ctx.data().db.update_config(config.id, config).await?;
-    ctx.say(format!(
-        "Updated heating-budget to {}€.",
-        config.monthly_budget_heating
-    ))
-    .await?;
-    Ok(())
+pub async fn budget_heating(
+    ctx: Context<'_>,
+    monthly_budget_heating: String,
+    #[description = "Date from which the value applies (YYYY-MM-DD), defaults to today"]
+    valid_from: Option<String>,
+) -> Result<(), Error> {
+    set(
+        ctx,
+        Setting::BudgetHeating,
+        monthly_budget_heating,
+        valid_from,
+    )
+    .await
 }
 
 /// Update the general-budget.
 #[poise::command(prefix_command, slash_command, rename = "general")]
-pub async fn budget_general(ctx: Context<'_>, monthly_budget_general: String) -> Result<(), Error> {
-    let mut config = ctx.data().db.get_config().await?;
-    config = config.with_monthly_budget_general(monthly_budget_general.parse::<f64>()?);
-    config = ctx.data().db.update_config(config.id, config).await?;
-    ctx.say(format!(
-        "Updated general-budget to {}€.",
-        config.monthly_budget_general
-    ))
-    .await?;
+pub async fn budget_general(
+    ctx: Context<'_>,
+    monthly_budget_general: String,
+    #[description = "Date from which the value applies (YYYY-MM-DD), defaults to today"]
+    valid_from: Option<String>,
+) -> Result<(), Error> {
+    set(
+        ctx,
+        Setting::BudgetGeneral,
+        monthly_budget_general,
+        valid_from,
+    )
+    .await
+}
+
+/// Update the monthly base fee of the heating-meter.
+#[poise::command(prefix_command, slash_command, rename = "heating")]
+pub async fn fee_heating(
+    ctx: Context<'_>,
+    base_fee_heating: String,
+    #[description = "Date from which the value applies (YYYY-MM-DD), defaults to today"]
+    valid_from: Option<String>,
+) -> Result<(), Error> {
+    set(ctx, Setting::FeeHeating, base_fee_heating, valid_from).await
+}
+
+/// Update the monthly base fee of the general-meter.
+#[poise::command(prefix_command, slash_command, rename = "general")]
+pub async fn fee_general(
+    ctx: Context<'_>,
+    base_fee_general: String,
+    #[description = "Date from which the value applies (YYYY-MM-DD), defaults to today"]
+    valid_from: Option<String>,
+) -> Result<(), Error> {
+    set(ctx, Setting::FeeGeneral, base_fee_general, valid_from).await
+}
+
+/// Update the schedule of the daily summary, e.g. `0 0 12 * * * *` for every day at noon.
+#[poise::command(prefix_command, slash_command, rename = "schedule")]
+pub async fn config_schedule(
+    ctx: Context<'_>,
+    #[description = "Cron-expression with seconds: sec min hour day month weekday [year]"]
+    cron: String,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let message = commands::set_schedule(&data.db, &data.scheduler, &cron, &author(ctx)).await?;
+    ctx.say(message).await?;
     Ok(())
 }
 
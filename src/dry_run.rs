@@ -0,0 +1,225 @@
+use crate::{
+    db::{
+        Aggregate, Config, ConfigHistory, CreateDay, Day, Days, Db, Device, Granularity, Hour,
+        JobRun, Peak, Storage, Trigger,
+    },
+    notify::{Kind, Notifier},
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// [Storage] that reads from the actual database, but only prints what would be written.
+/// Written days, hours and peaks are kept in memory, so the rest of a run sees them like it would normally.
+/// Stored days aren't loaded, so that [fetch::create_day](crate::fetch::create_day) always fetches them again.
+pub struct DryRun {
+    db: Db,
+    days: Mutex<BTreeMap<NaiveDate, Day>>,
+    hours: Mutex<Vec<Hour>>,
+    peaks: Mutex<BTreeMap<NaiveDate, Peak>>,
+}
+
+impl DryRun {
+    pub fn new(db: Db) -> Self {
+        DryRun {
+            db,
+            days: Mutex::new(BTreeMap::new()),
+            hours: Mutex::new(Vec::new()),
+            peaks: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Prints the day instead of saving it and keeps it for the rest of the run.
+    fn print_day(&self, day: Day) -> Result<Day> {
+        println!(
+            "Would save day {}: heating {:.2} kWh, general {:.2} kWh, {:.2} °C",
+            day.date, day.heating_consumption, day.general_consumption, day.average_temperature
+        );
+        lock(&self.days)?.insert(day.date, day.clone());
+        Ok(day)
+    }
+}
+
+/// Locks the mutex, turning a poisoned lock into an error.
+fn lock<T>(mutex: &Mutex<T>) -> Result<std::sync::MutexGuard<'_, T>> {
+    mutex
+        .lock()
+        .map_err(|_| anyhow!("Dry-run storage is poisoned."))
+}
+
+#[async_trait]
+impl Storage for DryRun {
+    async fn save_days(&self, days: Vec<CreateDay>) -> Result<()> {
+        for day in days {
+            self.print_day(Day::from(day))?;
+        }
+        Ok(())
+    }
+
+    async fn save_import(&self, day: CreateDay, hours: Vec<Hour>, peak: Peak) -> Result<Day> {
+        let day = self.print_day(Day::from(day))?;
+        for hour in &hours {
+            println!(
+                "Would save hour {}: heating {:.3} kWh, general {:.3} kWh",
+                hour.timestamp, hour.heating_consumption, hour.general_consumption
+            );
+        }
+        lock(&self.hours)?.extend(hours);
+        println!(
+            "Would save peak of {}: heating {:.3} kWh, general {:.3} kWh",
+            peak.date, peak.heating_max, peak.general_max
+        );
+        lock(&self.peaks)?.insert(peak.date, peak);
+        Ok(day)
+    }
+
+    async fn get_day(&self, date: NaiveDate) -> Result<Option<Day>> {
+        Ok(lock(&self.days)?.get(&date).cloned())
+    }
+
+    async fn get_latest_day(&self) -> Result<Option<Day>> {
+        let stored = self.db.get_latest_day().await?;
+        let written = lock(&self.days)?.values().next_back().cloned();
+        Ok(match (stored, written) {
+            (Some(stored), Some(written)) if stored.date > written.date => Some(stored),
+            (stored, written) => written.or(stored),
+        })
+    }
+
+    async fn get_days_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Days> {
+        let mut days: BTreeMap<NaiveDate, Day> = self
+            .db
+            .get_days_between(from, to)
+            .await?
+            .0
+            .into_iter()
+            .map(|day| (day.date, day))
+            .collect();
+        for (date, day) in lock(&self.days)?.range(from..=to) {
+            days.insert(*date, day.clone());
+        }
+        Ok(Days(days.into_values().collect()))
+    }
+
+    async fn get_hours_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Hour>> {
+        let written = lock(&self.hours)?.clone();
+        let mut hours: BTreeMap<_, _> = self
+            .db
+            .get_hours_between(from, to)
+            .await?
+            .into_iter()
+            .filter(|hour| !written.iter().any(|written| written.date == hour.date))
+            .map(|hour| (hour.timestamp, hour))
+            .collect();
+        for hour in written {
+            if hour.date >= from && hour.date <= to {
+                hours.insert(hour.timestamp, hour);
+            }
+        }
+        Ok(hours.into_values().collect())
+    }
+
+    async fn get_peaks_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Peak>> {
+        let mut peaks: BTreeMap<NaiveDate, Peak> = self
+            .db
+            .get_peaks_between(from, to)
+            .await?
+            .into_iter()
+            .map(|peak| (peak.date, peak))
+            .collect();
+        for (date, peak) in lock(&self.peaks)?.range(from..=to) {
+            peaks.insert(*date, peak.clone());
+        }
+        Ok(peaks.into_values().collect())
+    }
+
+    /// NOTE this only aggregates the stored days, not the ones written during the dry run.
+    async fn aggregate(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        granularity: Granularity,
+    ) -> Result<Vec<Aggregate>> {
+        self.db.aggregate(from, to, granularity).await
+    }
+
+    async fn get_config_history(&self) -> Result<ConfigHistory> {
+        self.db.get_config_history().await
+    }
+
+    async fn save_config(&self, config: Config) -> Result<Config> {
+        println!(
+            "Would save config valid from {}: {}",
+            config.valid_from,
+            serde_json::to_string(&config)?
+        );
+        Ok(config)
+    }
+
+    async fn get_schedule(&self, name: &str) -> Result<Option<String>> {
+        self.db.get_schedule(name).await
+    }
+
+    async fn save_schedule(&self, name: &str, cron: &str) -> Result<()> {
+        println!("Would save schedule of {}: {}", name, cron);
+        Ok(())
+    }
+
+    async fn get_devices(&self) -> Result<Vec<Device>> {
+        self.db.get_devices().await
+    }
+
+    async fn save_devices(&self, devices: Vec<Device>) -> Result<()> {
+        for device in devices {
+            println!(
+                "Would save device {}: {}{}",
+                device.device_id,
+                device.name,
+                if device.main_device {
+                    " (main device)"
+                } else {
+                    ""
+                }
+            );
+        }
+        Ok(())
+    }
+
+    async fn start_job_run(&self, job: &str, trigger: Trigger) -> Result<i64> {
+        println!("Would save start of {}-run ({})", job, trigger.as_str());
+        Ok(0)
+    }
+
+    async fn finish_job_run(
+        &self,
+        _id: i64,
+        error: Option<String>,
+        days: Vec<NaiveDate>,
+    ) -> Result<()> {
+        println!(
+            "Would save end of run: {}{}, wrote [{}]",
+            JobRun::status_of(&error),
+            error
+                .map(|error| format!(" ({})", error))
+                .unwrap_or_default(),
+            JobRun::join_days(&days)
+        );
+        Ok(())
+    }
+
+    async fn get_job_runs(&self, limit: i64) -> Result<Vec<JobRun>> {
+        self.db.get_job_runs(limit).await
+    }
+}
+
+/// [Notifier] that prints the messages instead of sending them.
+pub struct Print;
+
+#[async_trait]
+impl Notifier for Print {
+    async fn send(&self, kind: Kind, message: &str) -> Result<()> {
+        println!("Would send {:?}-message:\n{}", kind, message);
+        Ok(())
+    }
+}
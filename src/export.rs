@@ -0,0 +1,242 @@
+use crate::{
+    cli::ExportArgs,
+    db::{Db, Hour},
+    util,
+};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use std::{collections::BTreeMap, fs, io::Write};
+
+/// The file-format of an export.
+#[derive(poise::ChoiceParameter, clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    #[name = "CSV"]
+    Csv,
+    #[name = "JSON"]
+    Json,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+        }
+    }
+}
+
+/// The header of a CSV-export with a row per day, in the order of the fields of [ExportDay].
+const DAY_COLUMNS: [&str; 6] = [
+    "date",
+    "heating_consumption",
+    "general_consumption",
+    "average_temperature",
+    "heating_cost",
+    "general_cost",
+];
+
+/// The header of a CSV-export with a row per hour, in the order of the fields of [ExportHour].
+const HOUR_COLUMNS: [&str; 4] = [
+    "timestamp",
+    "date",
+    "heating_consumption",
+    "general_consumption",
+];
+
+/// A day as it is exported, with costs at the price valid on that day.
+#[derive(Serialize)]
+pub struct ExportDay {
+    date: NaiveDate,
+    heating_consumption: f64,
+    general_consumption: f64,
+    average_temperature: f64,
+    heating_cost: f64,
+    general_cost: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hours: Option<Vec<ExportHour>>,
+}
+
+/// An hour as it is exported.
+#[derive(Serialize)]
+struct ExportHour {
+    timestamp: DateTime<Utc>,
+    date: NaiveDate,
+    heating_consumption: f64,
+    general_consumption: f64,
+}
+
+/// Get the days between both dates (including both) with their costs and - if `hourly` is set - their hours.
+pub async fn days(db: &Db, from: NaiveDate, to: NaiveDate, hourly: bool) -> Result<Vec<ExportDay>> {
+    let configs = db.get_config_history().await?;
+    let mut hours: BTreeMap<NaiveDate, Vec<ExportHour>> = BTreeMap::new();
+    if hourly {
+        for hour in db.get_hours_between(from, to).await? {
+            hours.entry(hour.date).or_default().push(hour.into());
+        }
+    }
+
+    let mut days = Vec::new();
+    for day in db.get_days_between(from, to).await?.0 {
+        let config = configs.at(day.date)?;
+        days.push(ExportDay {
+            date: day.date,
+            heating_consumption: day.heating_consumption,
+            general_consumption: day.general_consumption,
+            average_temperature: day.average_temperature,
+            heating_cost: day.heating_cost(config),
+            general_cost: day.general_cost(config),
+            hours: hourly.then(|| hours.remove(&day.date).unwrap_or_default()),
+        });
+    }
+    Ok(days)
+}
+
+/// Export the days between both dates (including both).
+///
+/// JSON contains a list of days, each with its hours if `hourly` is set.
+/// As CSV can't nest values, `hourly` exports one row per hour instead of one row per day.
+pub async fn export(
+    db: &Db,
+    from: NaiveDate,
+    to: NaiveDate,
+    format: Format,
+    hourly: bool,
+) -> Result<Vec<u8>> {
+    match format {
+        Format::Json => Ok(serde_json::to_vec_pretty(
+            &days(db, from, to, hourly).await?,
+        )?),
+        Format::Csv => {
+            // the header is written explicitly, as the writer would only derive it from the first row
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(Vec::new());
+            writer.write_record(if hourly {
+                &HOUR_COLUMNS[..]
+            } else {
+                &DAY_COLUMNS[..]
+            })?;
+            for day in days(db, from, to, hourly).await? {
+                match day.hours {
+                    Some(hours) => {
+                        for hour in hours {
+                            writer.serialize(hour)?;
+                        }
+                    }
+                    None => writer.serialize(day)?,
+                }
+            }
+            Ok(writer.into_inner()?)
+        }
+    }
+}
+
+/// Runs the `export`-subcommand, writing to the given file or stdout.
+pub async fn run(db: &Db, args: ExportArgs) -> Result<()> {
+    let today = util::today();
+    let from = args.from.unwrap_or(util::first_of_month(today));
+    let to = args.to.unwrap_or(today);
+    let data = export(db, from, to, args.format, args.hourly).await?;
+
+    match args.output {
+        Some(path) => fs::write(path, data)?,
+        None => std::io::stdout().write_all(&data)?,
+    }
+    Ok(())
+}
+
+impl From<Hour> for ExportHour {
+    fn from(hour: Hour) -> Self {
+        ExportHour {
+            timestamp: hour.timestamp,
+            date: hour.date,
+            heating_consumption: hour.heating_consumption,
+            general_consumption: hour.general_consumption,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{CreateDay, Peak},
+        memory::Memory,
+    };
+    use std::sync::Arc;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    fn csv(data: Vec<u8>) -> Vec<String> {
+        String::from_utf8(data)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn empty_csv_has_a_header() {
+        let db: Db = Arc::new(Memory::new());
+        for (hourly, columns) in [(false, &DAY_COLUMNS[..]), (true, &HOUR_COLUMNS[..])] {
+            let lines = csv(export(&db, date(1), date(31), Format::Csv, hourly)
+                .await
+                .unwrap());
+            assert_eq!(lines, vec![columns.join(",")]);
+        }
+    }
+
+    #[tokio::test]
+    async fn csv_header_matches_the_fields() {
+        let db: Db = Arc::new(Memory::new());
+        let timestamp = date(2).and_hms_opt(10, 0, 0).unwrap().and_utc();
+        let hour = Hour {
+            timestamp,
+            date: date(2),
+            heating_consumption: 1.0,
+            general_consumption: 0.5,
+        };
+        db.save_import(
+            CreateDay {
+                heating_consumption: 10.0,
+                general_consumption: 4.0,
+                average_temperature: 5.0,
+                date: date(2),
+            },
+            vec![hour.clone()],
+            Peak {
+                date: date(2),
+                heating_max: 1.0,
+                general_max: 0.5,
+            },
+        )
+        .await
+        .unwrap();
+
+        // the header the writer derives from the fields
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let day = days(&db, date(1), date(31), false).await.unwrap().remove(0);
+        writer.serialize(&day).unwrap();
+        assert_eq!(csv(writer.into_inner().unwrap())[0], DAY_COLUMNS.join(","));
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.serialize(ExportHour::from(hour)).unwrap();
+        assert_eq!(csv(writer.into_inner().unwrap())[0], HOUR_COLUMNS.join(","));
+
+        let lines = csv(export(&db, date(1), date(31), Format::Csv, true)
+            .await
+            .unwrap());
+        assert_eq!(
+            lines,
+            vec![
+                HOUR_COLUMNS.join(","),
+                format!(
+                    "{},2026-03-02,1.0,0.5",
+                    timestamp.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+                ),
+            ]
+        );
+    }
+}
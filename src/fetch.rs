@@ -0,0 +1,107 @@
+use crate::{
+    db::{CreateDay, Day, Db, Device, Hour, Peak},
+    meteo::Meteo,
+    metrics, mqtt,
+    powerfox::{Powerfox, Report},
+    util,
+};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::env;
+
+/// The name of the Powerfox-device measuring the consumption for heating, if `POWERFOX_HEATING_DEVICE` isn't set.
+const DEFAULT_HEATING_DEVICE: &str = "Heizstrom";
+
+/// The name of the Powerfox-device measuring the general consumption, if `POWERFOX_GENERAL_DEVICE` isn't set.
+const DEFAULT_GENERAL_DEVICE: &str = "Allgemeinstrom";
+
+/// The reports of both meters for a single day.
+struct Reports {
+    heating: Report,
+    general: Report,
+}
+
+/// Create the entry for the given date, including its hours. Loads from the database if it exists already.
+pub async fn create_day(db: &Db, date: NaiveDate) -> Result<Day> {
+    // if we have the data already, just return it to save on API-calls
+    if let Some(day) = db.get_day(date).await? {
+        return Ok(day);
+    }
+
+    let meteo = Meteo::new()?;
+    let temperature = meteo.get_temperature_for(date).await?;
+    import_day(db, date, temperature.average_temperature()?).await
+}
+
+/// Fetch the given date from Powerfox and save it with its hours and peak, replacing it if it exists already.
+pub async fn import_day(db: &Db, date: NaiveDate, average_temperature: f64) -> Result<Day> {
+    let reports = get_reports(db, date).await?;
+    // hours and peak are saved with the day, so they're never missing for a stored day
+    let day = db
+        .save_import(
+            CreateDay::new(
+                date,
+                &reports.heating,
+                &reports.general,
+                average_temperature,
+            ),
+            Hour::from_reports(date, &reports.heating, &reports.general),
+            Peak::from_reports(date, &reports.heating, &reports.general),
+        )
+        .await?;
+    metrics::record_import();
+    if date == util::yesterday() {
+        mqtt::publish_yesterday(db, &day).await;
+    }
+    Ok(day)
+}
+
+/// Get the data for today up to now.
+/// NOTE this isn't saved as the day isn't over yet.
+pub async fn get_today(db: &Db) -> Result<Day> {
+    let meteo = Meteo::new()?;
+    let temperature = meteo.get_temperature_for_today().await?;
+
+    let reports = get_reports(db, util::today()).await?;
+    let day = Day::new(
+        reports.heating,
+        reports.general,
+        temperature.average_temperature()?,
+    );
+    mqtt::publish_today(db, &day).await;
+    Ok(day)
+}
+
+/// Get the reports of both meters for the given date and update the stored devices.
+async fn get_reports(db: &Db, date: NaiveDate) -> Result<Reports> {
+    let heating_device =
+        env::var("POWERFOX_HEATING_DEVICE").unwrap_or(DEFAULT_HEATING_DEVICE.to_string());
+    let general_device =
+        env::var("POWERFOX_GENERAL_DEVICE").unwrap_or(DEFAULT_GENERAL_DEVICE.to_string());
+    let powerfox = Powerfox::new()?;
+    let devices = powerfox.get_devices().await?;
+    db.save_devices(devices.iter().map(Device::from).collect())
+        .await?;
+
+    let mut heating_report = None;
+    let mut general_report = None;
+    for device in devices {
+        if device.name == heating_device {
+            heating_report = Some(powerfox.get_report(&device.device_id, date).await?);
+        }
+
+        if device.name == general_device {
+            general_report = Some(powerfox.get_report(&device.device_id, date).await?);
+        }
+
+        // checking this in the loop might be beneficial if there are multiple other devices
+        if heating_report.is_some() && general_report.is_some() {
+            break;
+        }
+    }
+
+    match (heating_report, general_report) {
+        (Some(heating), Some(general)) => Ok(Reports { heating, general }),
+        _ => Err(anyhow!("Could not get all necessary data for {}.", date)),
+    }
+}
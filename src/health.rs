@@ -0,0 +1,147 @@
+use crate::{api, cli::HealthArgs, db::Db, discord, scheduler::Scheduler, util};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{env, future::Future, sync::OnceLock};
+
+/// Used if `HEALTH_MAX_AGE` isn't set. The latest day ends at midnight and is imported by the daily job,
+/// so this leaves a day for the job to run before it's considered stale.
+const DEFAULT_MAX_AGE: i64 = 48;
+
+/// When the server was started, see [last_import].
+static STARTED: OnceLock<DateTime<Utc>> = OnceLock::new();
+
+/// Remembers when the server was started, which should happen once it's up.
+pub fn record_start() {
+    STARTED.get_or_init(Utc::now);
+}
+
+/// The health of the running server, see [Health::check].
+#[derive(Serialize, Deserialize)]
+pub struct Health {
+    pub healthy: bool,
+    pub checks: Vec<Check>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Check {
+    pub name: String,
+    pub healthy: bool,
+    pub detail: String,
+}
+
+impl Health {
+    /// Checks the connection to the database and Discord's gateway, whether the daily job is scheduled and
+    /// whether the latest stored day ended at most `HEALTH_MAX_AGE` hours ago.
+    pub async fn check(db: &Db, scheduler: &Scheduler) -> Self {
+        let mut checks = vec![
+            check("database", async {
+                let config = db.get_config().await?;
+                Ok(format!("config valid from {}", config.valid_from))
+            })
+            .await,
+        ];
+        if crate::frontends().iter().any(|name| name == "discord") {
+            checks.push(check("discord", discord::gateway()).await);
+        }
+        checks.push(
+            check("scheduler", async {
+                match scheduler.next_daily().await? {
+                    Some(next) => Ok(format!(
+                        "next daily run at {}",
+                        next.format("%Y-%m-%d %H:%M %Z")
+                    )),
+                    None => bail!("the daily job isn't scheduled"),
+                }
+            })
+            .await,
+        );
+        checks.push(check("import", last_import(db)).await);
+
+        Health {
+            healthy: checks.iter().all(|check| check.healthy),
+            checks,
+        }
+    }
+}
+
+async fn check(name: &str, check: impl Future<Output = Result<String>>) -> Check {
+    let (healthy, detail) = match check.await {
+        Ok(detail) => (true, detail),
+        Err(err) => (false, err.to_string()),
+    };
+    Check {
+        name: name.to_string(),
+        healthy,
+        detail,
+    }
+}
+
+/// How long ago the latest stored day ended, failing if that's longer than `HEALTH_MAX_AGE` hours.
+/// Without any day, a fresh deployment has until `HEALTH_MAX_AGE` hours after the start for the first import.
+async fn last_import(db: &Db) -> Result<String> {
+    let max_age = match env::var("HEALTH_MAX_AGE") {
+        Ok(hours) => hours.parse()?,
+        Err(_) => DEFAULT_MAX_AGE,
+    };
+    let Some(day) = db.get_latest_day().await? else {
+        let started = *STARTED.get_or_init(Utc::now);
+        let uptime = (Utc::now() - started).num_hours();
+        if uptime > max_age {
+            bail!(
+                "no day is stored yet, {} hours after the start, which is more than {}",
+                uptime,
+                max_age
+            );
+        }
+        return Ok(format!(
+            "no import yet, the server started {} hours ago",
+            uptime
+        ));
+    };
+    let end = util::start_of_day(day.date + Duration::days(1));
+    let age = (util::now() - end).num_hours();
+    if age > max_age {
+        bail!(
+            "the latest day {} ended {} hours ago, which is more than {}",
+            day.date,
+            age,
+            max_age
+        );
+    }
+    Ok(format!(
+        "the latest day {} ended {} hours ago",
+        day.date, age
+    ))
+}
+
+/// Runs the `health`-subcommand: asks the running server for its health and prints each check,
+/// failing unless it's healthy. This is used by the Docker-healthcheck.
+pub async fn request(args: HealthArgs) -> Result<()> {
+    let url = match args.url {
+        Some(url) => url,
+        None => {
+            let address = env::var("HTTP_ADDRESS").unwrap_or(api::DEFAULT_ADDRESS.to_string());
+            // the server may listen on every interface, but is reachable via localhost
+            format!("http://{}/health", address.replace("0.0.0.0", "127.0.0.1"))
+        }
+    };
+    let response = Client::new()
+        .get(&url)
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|err| anyhow!("Could not reach {}: {}", url, err))?;
+    let status = response.status();
+    let health: Health = response.json().await?;
+
+    for check in &health.checks {
+        let state = if check.healthy { "ok" } else { "FAILED" };
+        println!("{:<8}{}: {}", state, check.name, check.detail);
+    }
+    if !status.is_success() || !health.healthy {
+        bail!("The server is unhealthy.");
+    }
+    Ok(())
+}
@@ -0,0 +1,485 @@
+use crate::{
+    cli::ImportArgs,
+    db::{CreateDay, Db},
+    meteo::Meteo,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Duration, NaiveDate};
+use log::{info, warn};
+use std::collections::BTreeMap;
+
+/// A value read from the file, with its line for error-messages.
+struct Value {
+    line: u64,
+    value: f64,
+}
+
+/// The values of a single meter per date.
+type Values = BTreeMap<NaiveDate, Value>;
+
+/// Everything read from the file.
+struct Rows {
+    heating: Values,
+    general: Values,
+    temperatures: BTreeMap<NaiveDate, f64>,
+}
+
+/// Runs the `import`-subcommand.
+///
+/// Nothing is written unless the whole file is valid - every problem is listed at once instead.
+/// The days are then saved together, so a failing database doesn't leave a partial import either.
+pub async fn run(db: &Db, args: ImportArgs) -> Result<()> {
+    let mut problems = Vec::new();
+    let rows = read(&args, &mut problems)?;
+    let heating = daily_consumption(
+        &args.heating_meter,
+        &rows.heating,
+        args.cumulative,
+        &mut problems,
+    );
+    let general = daily_consumption(
+        &args.general_meter,
+        &rows.general,
+        args.cumulative,
+        &mut problems,
+    );
+
+    // every day holds both meters, so days with only one meter can't be imported
+    for (meter, days, other) in [
+        (&args.heating_meter, &heating, &general),
+        (&args.general_meter, &general, &heating),
+    ] {
+        let skipped: Vec<&NaiveDate> = days
+            .keys()
+            .filter(|date| !other.contains_key(date))
+            .collect();
+        if let (Some(first), Some(last)) = (skipped.first(), skipped.last()) {
+            warn!(
+                "Skipping {} days between {} and {} that only have values for the {}-meter.",
+                skipped.len(),
+                first,
+                last,
+                meter
+            );
+        }
+    }
+    let dates: Vec<NaiveDate> = heating
+        .keys()
+        .filter(|date| general.contains_key(date))
+        .copied()
+        .collect();
+    let (Some(&first), Some(&last)) = (dates.first(), dates.last()) else {
+        problems.push("There are no days with values for both meters.".to_string());
+        bail!(
+            "Could not import {}:\n{}",
+            args.file.display(),
+            problems.join("\n")
+        );
+    };
+
+    let stored: Vec<NaiveDate> = db
+        .get_days_between(first, last)
+        .await?
+        .0
+        .iter()
+        .map(|day| day.date)
+        .filter(|date| dates.contains(date))
+        .collect();
+    if !stored.is_empty() && !args.overwrite {
+        problems.push(format!(
+            "{} of the days are already stored, e.g. {} - use --overwrite to replace them.",
+            stored.len(),
+            stored[0]
+        ));
+    }
+
+    if !problems.is_empty() {
+        bail!(
+            "Could not import {}:\n{}",
+            args.file.display(),
+            problems.join("\n")
+        );
+    }
+
+    let temperatures = temperatures(&dates, rows.temperatures).await?;
+    db.save_days(
+        dates
+            .iter()
+            .map(|date| CreateDay {
+                heating_consumption: heating[date],
+                general_consumption: general[date],
+                average_temperature: temperatures[date],
+                date: *date,
+            })
+            .collect(),
+    )
+    .await?;
+
+    info!(
+        "Imported {} days between {} and {}, replacing {}.",
+        dates.len(),
+        first,
+        last,
+        stored.len()
+    );
+    Ok(())
+}
+
+/// Read the values of both meters and the temperatures, if there's a column for them.
+/// Missing columns fail immediately, while problems with single rows are collected.
+fn read(args: &ImportArgs, problems: &mut Vec<String>) -> Result<Rows> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(args.delimiter as u8)
+        .trim(csv::Trim::All)
+        .from_path(&args.file)?;
+
+    let headers = reader.headers()?.clone();
+    let column = |name: &String| {
+        headers
+            .iter()
+            .position(|header| header == name)
+            .ok_or(anyhow!(
+                "Column '{}' not found in {}, the columns are: {}.",
+                name,
+                args.file.display(),
+                headers.iter().collect::<Vec<_>>().join(", ")
+            ))
+    };
+    let date_column = column(&args.date_column)?;
+    let meter_column = column(&args.meter_column)?;
+    let value_column = column(&args.value_column)?;
+    let temperature_column = args.temperature_column.as_ref().map(column).transpose()?;
+
+    let mut rows = Rows {
+        heating: BTreeMap::new(),
+        general: BTreeMap::new(),
+        temperatures: BTreeMap::new(),
+    };
+    for record in reader.records() {
+        let record = record?;
+        let line = record
+            .position()
+            .map(|position| position.line())
+            .unwrap_or_default();
+        let field = |column: usize| record.get(column).unwrap_or_default();
+
+        let date = match NaiveDate::parse_from_str(field(date_column), &args.date_format) {
+            Ok(date) => date,
+            Err(_) => {
+                problems.push(format!(
+                    "Line {}: '{}' is not a date in the format '{}'.",
+                    line,
+                    field(date_column),
+                    args.date_format
+                ));
+                continue;
+            }
+        };
+        let value = match field(value_column).parse::<f64>() {
+            Ok(value) => value,
+            Err(_) => {
+                problems.push(format!(
+                    "Line {}: '{}' is not a number.",
+                    line,
+                    field(value_column)
+                ));
+                continue;
+            }
+        };
+        if let Some(column) = temperature_column {
+            match field(column).parse::<f64>() {
+                Ok(temperature) => {
+                    rows.temperatures.insert(date, temperature);
+                }
+                Err(_) => problems.push(format!(
+                    "Line {}: '{}' is not a temperature.",
+                    line,
+                    field(column)
+                )),
+            }
+        }
+
+        let meter = field(meter_column);
+        let values = if meter == args.heating_meter {
+            &mut rows.heating
+        } else if meter == args.general_meter {
+            &mut rows.general
+        } else {
+            problems.push(format!(
+                "Line {}: unknown meter '{}', expected '{}' or '{}'.",
+                line, meter, args.heating_meter, args.general_meter
+            ));
+            continue;
+        };
+        if let Some(existing) = values.get(&date) {
+            problems.push(format!(
+                "Line {}: the {}-meter already has a value for {} in line {}.",
+                line, meter, date, existing.line
+            ));
+            continue;
+        }
+        values.insert(date, Value { line, value });
+    }
+    Ok(rows)
+}
+
+/// Get the consumption per day from the values of a meter.
+///
+/// Meter readings are taken at the end of each day, so the first reading only serves as the start.
+/// If there are days between two readings, the consumption is spread evenly over them.
+fn daily_consumption(
+    meter: &str,
+    values: &Values,
+    cumulative: bool,
+    problems: &mut Vec<String>,
+) -> BTreeMap<NaiveDate, f64> {
+    let mut days = BTreeMap::new();
+    let pairs = values.iter().zip(values.iter().skip(1));
+
+    if !cumulative {
+        for (date, value) in values {
+            if value.value < 0.0 {
+                problems.push(format!(
+                    "Line {}: the consumption of the {}-meter is negative.",
+                    value.line, meter
+                ));
+            }
+            days.insert(*date, value.value);
+        }
+        for ((previous, _), (date, _)) in pairs {
+            if *date - *previous > Duration::days(1) {
+                warn!(
+                    "There are no values for the {}-meter between {} and {}.",
+                    meter, previous, date
+                );
+            }
+        }
+        return days;
+    }
+
+    for ((previous_date, previous), (date, value)) in pairs {
+        let consumption = value.value - previous.value;
+        if consumption < 0.0 {
+            problems.push(format!(
+                "Line {}: the reading of the {}-meter is lower than the one in line {}.",
+                value.line, meter, previous.line
+            ));
+            continue;
+        }
+
+        let span = (*date - *previous_date).num_days();
+        if span > 1 {
+            warn!(
+                "Spreading {:.2} kWh of the {}-meter evenly over the {} days until {}.",
+                consumption, meter, span, date
+            );
+        }
+        for offset in 1..=span {
+            days.insert(
+                *previous_date + Duration::days(offset),
+                consumption / span as f64,
+            );
+        }
+    }
+    days
+}
+
+/// Get the temperature of every date, fetching those that weren't in the file from the weather-archive.
+async fn temperatures(
+    dates: &[NaiveDate],
+    mut temperatures: BTreeMap<NaiveDate, f64>,
+) -> Result<BTreeMap<NaiveDate, f64>> {
+    let missing: Vec<&NaiveDate> = dates
+        .iter()
+        .filter(|date| !temperatures.contains_key(date))
+        .collect();
+    if let (Some(first), Some(last)) = (missing.first(), missing.last()) {
+        info!("Getting temperatures from {} to {}.", first, last);
+        let meteo = Meteo::new()
+            .context("Temperatures that aren't in the file are fetched from the weather-archive, which isn't configured.")?;
+        let archive = meteo.get_daily_temperatures(**first, **last).await?;
+        for date in missing {
+            // the archive has no temperatures for the last few days yet
+            let temperature = match archive.get(date) {
+                Some(temperature) => *temperature,
+                None => meteo
+                    .get_temperature_for(*date)
+                    .await
+                    .and_then(|data| data.average_temperature())
+                    .with_context(|| format!("Could not get the temperature for {}.", date))?,
+            };
+            temperatures.insert(*date, temperature);
+        }
+    }
+    Ok(temperatures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memory;
+    use std::{path::PathBuf, sync::Arc};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    /// The values of a meter, as if read from consecutive lines after the header.
+    fn values(readings: &[(u32, f64)]) -> Values {
+        readings
+            .iter()
+            .zip(2..)
+            .map(|((day, value), line)| {
+                (
+                    date(*day),
+                    Value {
+                        line,
+                        value: *value,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Writes the CSV to a file of its own, so tests can run in parallel.
+    fn file(name: &str, csv: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("powerfox-import-{}.csv", name));
+        std::fs::write(&path, csv).unwrap();
+        path
+    }
+
+    fn args(file: PathBuf, overwrite: bool) -> ImportArgs {
+        ImportArgs {
+            file,
+            date_column: "date".to_string(),
+            meter_column: "meter".to_string(),
+            value_column: "value".to_string(),
+            temperature_column: Some("temperature".to_string()),
+            heating_meter: "heating".to_string(),
+            general_meter: "general".to_string(),
+            cumulative: false,
+            date_format: "%Y-%m-%d".to_string(),
+            delimiter: ',',
+            overwrite,
+        }
+    }
+
+    #[test]
+    fn cumulative_readings_are_spread_over_gaps() {
+        let mut problems = Vec::new();
+        let days = daily_consumption(
+            "heating",
+            &values(&[(1, 100.0), (2, 110.0), (5, 140.0)]),
+            true,
+            &mut problems,
+        );
+        assert!(problems.is_empty());
+        assert_eq!(
+            days,
+            BTreeMap::from([
+                (date(2), 10.0),
+                (date(3), 10.0),
+                (date(4), 10.0),
+                (date(5), 10.0)
+            ])
+        );
+    }
+
+    #[test]
+    fn meter_resets_are_problems() {
+        let mut problems = Vec::new();
+        let days = daily_consumption(
+            "heating",
+            &values(&[(1, 100.0), (2, 110.0), (3, 5.0), (4, 15.0)]),
+            true,
+            &mut problems,
+        );
+        assert_eq!(
+            problems,
+            vec!["Line 4: the reading of the heating-meter is lower than the one in line 3."]
+        );
+        // the readings after the reset still count from the new value
+        assert_eq!(days, BTreeMap::from([(date(2), 10.0), (date(4), 10.0)]));
+    }
+
+    #[test]
+    fn negative_consumption_is_a_problem() {
+        let mut problems = Vec::new();
+        let days = daily_consumption(
+            "general",
+            &values(&[(1, 3.0), (2, -1.0), (4, 2.0)]),
+            false,
+            &mut problems,
+        );
+        assert_eq!(
+            problems,
+            vec!["Line 3: the consumption of the general-meter is negative."]
+        );
+        // gaps aren't filled without readings
+        assert_eq!(days.len(), 3);
+        assert!(!days.contains_key(&date(3)));
+    }
+
+    #[tokio::test]
+    async fn duplicate_dates_are_problems() {
+        let db: Db = Arc::new(Memory::new());
+        let path = file(
+            "duplicates",
+            "date,meter,value,temperature\n\
+             2026-03-01,heating,10,5\n\
+             2026-03-01,general,3,5\n\
+             2026-03-01,heating,12,5\n",
+        );
+        let err = run(&db, args(path, false)).await.unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Line 4: the heating-meter already has a value for 2026-03-01 in line 2."));
+        assert!(db
+            .get_days_between(date(1), date(31))
+            .await
+            .unwrap()
+            .0
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn stored_days_are_only_replaced_with_overwrite() {
+        let db: Db = Arc::new(Memory::new());
+        db.save_days(vec![CreateDay {
+            heating_consumption: 1.0,
+            general_consumption: 1.0,
+            average_temperature: 1.0,
+            date: date(2),
+        }])
+        .await
+        .unwrap();
+        let path = file(
+            "overlap",
+            "date,meter,value,temperature\n\
+             2026-03-01,heating,10,5\n\
+             2026-03-01,general,3,5\n\
+             2026-03-02,heating,12,4\n\
+             2026-03-02,general,4,4\n",
+        );
+
+        let err = run(&db, args(path.clone(), false)).await.unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("1 of the days are already stored, e.g. 2026-03-02"));
+        assert_eq!(
+            db.get_days_between(date(1), date(31))
+                .await
+                .unwrap()
+                .0
+                .len(),
+            1
+        );
+
+        run(&db, args(path, true)).await.unwrap();
+        let days = db.get_days_between(date(1), date(31)).await.unwrap().0;
+        assert_eq!(days.len(), 2);
+        let replaced = days.iter().find(|day| day.date == date(2)).unwrap();
+        assert_eq!(replaced.heating_consumption, 12.0);
+        assert_eq!(replaced.average_temperature, 4.0);
+    }
+}
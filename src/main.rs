@@ -1,79 +1,141 @@
-use crate::discord::{say, start_bot};
-use anyhow::Result;
+use crate::discord::start_bot;
+use anyhow::{bail, Result};
+use clap::Parser;
+use cli::{Cli, Command};
 use db::Db;
 use dotenv::dotenv;
 use env_logger::{Builder, Target};
-use log::{error, info};
+use log::{info, LevelFilter};
+use notify::Notifiers;
 use poise::serenity_prelude as serenity;
-use serenity::model::prelude::*;
+use scheduler::Scheduler;
 use std::env;
-use tokio_cron_scheduler::{Job, JobScheduler};
+use tokio::task::JoinSet;
 
+mod anomaly;
+mod api;
+mod backfill;
+mod baseload;
+mod check;
+mod cli;
+mod commands;
 mod db;
 mod discord;
+mod dry_run;
+mod export;
+mod fetch;
+mod health;
+mod import;
+mod manage;
+mod markup;
+mod matrix;
+mod memory;
 mod meteo;
+mod metrics;
+mod mqtt;
+mod notify;
+mod postgres;
 mod powerfox;
+mod profile;
+mod scheduler;
+mod settings;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod telegram;
 mod util;
 
+/// The frontends answering commands if `FRONTENDS` isn't set.
+const DEFAULT_FRONTENDS: &str = "discord";
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Command::Serve);
+
+    // other commands may write their output to stdout, which shouldn't be mixed with logs
+    // - they also report their progress by default
     let mut builder = Builder::from_default_env();
-    builder.target(Target::Stdout);
+    match command {
+        Command::Serve => builder.target(Target::Stdout),
+        _ if env::var("RUST_LOG").is_err() => builder
+            .target(Target::Stderr)
+            .filter_module("powerfox", LevelFilter::Info),
+        _ => builder.target(Target::Stderr),
+    };
     builder.init();
 
     info!("Starting app.");
     dotenv().ok();
-    // TODO the bot-permission-stiff is the important part
+    settings::load_secrets()?;
+    if let Some(path) = settings::load()? {
+        info!("Read config-file {}.", path.display());
+    }
+    // only asks the running server, which checks everything itself
+    if let Command::Health(args) = command {
+        return health::request(args).await;
+    }
+    settings::validate(&command)?;
 
     // setup
-    let db = Db::new().await?;
-    let cloned_db = db.clone();
-    let token = env::var("DISCORD_TOKEN")?;
-    let intents = serenity::GatewayIntents::non_privileged();
-
-    // schedule the daily message
-    let sched = JobScheduler::new().await?;
-
-    // schedule daily job
-    sched
-        .add(Job::new_async("0 0 12 * * * *", move |_uuid, mut _l| {
-            let db = db.clone();
-            Box::pin(async move {
-                let token = env::var("DISCORD_TOKEN").expect("Could not find DISCORD_TOKEN");
-                powerfox_daily(&token.clone(), &db)
-                    .await
-                    .expect("Could not execute daily task.");
-            })
-        })?)
-        .await?;
-    sched.start().await?;
-
-    // start the bot
-    // TODO check if we can use &Db
-    start_bot(&token, intents, cloned_db).await?;
+    let timezone = util::init_timezone()?;
+    info!("Using timezone {}.", timezone);
 
-    Ok(())
+    // connects per command, as check reports a failing database like any other check
+    match command {
+        Command::Serve => serve(db::connect().await?).await,
+        Command::Export(args) => export::run(&db::connect().await?, args).await,
+        Command::Import(args) => import::run(&db::connect().await?, args).await,
+        Command::RunDaily(args) => manage::run_daily(args).await,
+        Command::Backfill(args) => backfill::run(&db::connect().await?, args).await,
+        Command::Config(command) => manage::config(&db::connect().await?, command).await,
+        Command::Devices(command) => manage::devices(&db::connect().await?, command).await,
+        Command::Check => check::run().await,
+        Command::Health(args) => health::request(args).await,
+    }
 }
 
-async fn powerfox_daily(token: &str, db: &Db) -> Result<()> {
-    info!("Writing yesterday's data to Discord.");
-    let channel_id = ChannelId::new(env::var("DISCORD_CHANNEL_ID")?.parse()?);
-    say(token, channel_id, "Getting yesterday's data.".to_string()).await?;
+/// Start the scheduler, the bot and the HTTP-API, which run until the app is stopped.
+async fn serve(db: Db) -> Result<()> {
+    health::record_start();
+    mqtt::init().await?;
 
-    match db.create_yesterday().await {
-        Ok(day) => {
-            let config = db.get_config().await?;
-            say(token, channel_id, day.summary(&config.cost_heating)).await?;
+    // schedule the daily message
+    let notifiers = Notifiers::from_env()?;
+    let scheduler = Scheduler::start(db.clone(), notifiers).await?;
 
-            let days = db.get_days_of_month().await?;
-            say(token, channel_id, days.summary(&config)?).await?;
-            info!("Done with daily data and summary.")
-        }
-        Err(err) => {
-            let error = format!("Encountered an error: {}", err);
-            error!("{}", error);
-            say(token, channel_id, error).await?;
-        }
+    // start the HTTP-API and the frontends, stopping if any of them fails
+    let mut tasks = JoinSet::new();
+    tasks.spawn(api::serve(db.clone(), scheduler.clone()));
+    for frontend in frontends() {
+        match frontend.as_str() {
+            "discord" => {
+                let token = env::var("DISCORD_TOKEN")?;
+                let intents = serenity::GatewayIntents::non_privileged();
+                tasks.spawn(start_bot(token, intents, db.clone(), scheduler.clone()))
+            }
+            "matrix" => tasks.spawn(matrix::start_bot(db.clone(), scheduler.clone())),
+            "telegram" => tasks.spawn(telegram::start_bot(db.clone(), scheduler.clone())),
+            _ => bail!(
+                "Unknown frontend '{}', use discord, matrix or telegram.",
+                frontend
+            ),
+        };
+        info!("Starting {}-frontend.", frontend);
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        result??;
     }
     Ok(())
 }
+
+/// The comma-separated frontends from `FRONTENDS`.
+fn frontends() -> Vec<String> {
+    env::var("FRONTENDS")
+        .unwrap_or(DEFAULT_FRONTENDS.to_string())
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,105 @@
+use crate::{
+    cli::{ConfigCommand, DevicesCommand, RunDailyArgs},
+    commands::{self, Setting},
+    db::{self, Db, Device, Trigger},
+    dry_run::{DryRun, Print},
+    notify::Notifiers,
+    powerfox::Powerfox,
+    scheduler::{self, Scheduler},
+    util,
+};
+use anyhow::Result;
+use clap::ValueEnum;
+use std::{env, sync::Arc};
+
+/// Runs the `run-daily`-subcommand, sending the summary like the scheduled job does.
+/// With `--dry-run`, nothing is saved or sent and the database is opened read-only, see [DryRun].
+pub async fn run_daily(args: RunDailyArgs) -> Result<()> {
+    let date = args.date.unwrap_or(util::yesterday());
+    if args.dry_run {
+        let db: Db = Arc::new(DryRun::new(db::connect_read_only().await?));
+        let notifiers = Notifiers::all(Arc::new(Print));
+        return scheduler::powerfox_daily(&db, &notifiers, date, Trigger::Cli).await;
+    }
+    let notifiers = Notifiers::from_env()?;
+    let db = db::connect().await?;
+    scheduler::powerfox_daily(&db, &notifiers, date, Trigger::Cli).await
+}
+
+/// Runs the `config`-subcommands.
+pub async fn config(db: &Db, command: ConfigCommand) -> Result<()> {
+    match command {
+        ConfigCommand::Get { history: true } => println!("{}", commands::history(db).await?),
+        ConfigCommand::Get { history: false } => {
+            let config = db.get_config().await?;
+            println!("valid-from: {}", config.valid_from);
+            for setting in Setting::value_variants() {
+                if let Some(name) = setting.to_possible_value() {
+                    println!(
+                        "{}: {}{}",
+                        name.get_name(),
+                        setting.get(&config),
+                        setting.unit()
+                    );
+                }
+            }
+            println!(
+                "schedule: {} ({})",
+                Scheduler::daily_schedule(db).await?,
+                util::timezone()
+            );
+        }
+        ConfigCommand::Set {
+            setting,
+            value,
+            valid_from,
+        } => {
+            let valid_from = valid_from.map(|date| date.to_string());
+            let message = commands::set(db, setting, &value, valid_from, &author()).await?;
+            println!("{}", message);
+        }
+        ConfigCommand::Schedule { cron } => {
+            Scheduler::save_daily_schedule(db, &cron).await?;
+            println!(
+                "Updated daily schedule to '{}' ({}), a running server uses it once it's restarted.",
+                cron,
+                util::timezone()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Runs the `devices`-subcommands.
+pub async fn devices(db: &Db, command: DevicesCommand) -> Result<()> {
+    match command {
+        DevicesCommand::List { refresh } => {
+            if refresh {
+                let devices = Powerfox::new()?.get_devices().await?;
+                db.save_devices(devices.iter().map(Device::from).collect())
+                    .await?;
+            }
+            let devices = db.get_devices().await?;
+            if devices.is_empty() {
+                println!("No devices yet, use --refresh to get them from Powerfox.");
+            }
+            for device in devices {
+                let main = if device.main_device {
+                    " (main device)"
+                } else {
+                    ""
+                };
+                println!("{}\t{}{}", device.device_id, device.name, main);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The author of changes made with the CLI, as logged by [commands::set].
+fn author() -> String {
+    format!(
+        "{} (CLI)",
+        env::var("USER").unwrap_or("<unknown>".to_string())
+    )
+}
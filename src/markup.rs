@@ -0,0 +1,148 @@
+/// Renders the Discord-markdown that answers are written in - bold text, inline code, code blocks and quotes -
+/// as the HTML understood by Matrix and Telegram. Lines outside of code blocks are separated by `line_break`,
+/// because Matrix ignores newlines in HTML while Telegram doesn't support `<br>`.
+pub fn to_html(text: &str, line_break: &str) -> String {
+    let mut html = String::new();
+    let mut code_block = false;
+    for line in text.lines() {
+        if line.starts_with("```") {
+            if code_block {
+                html.push_str("</pre>");
+            } else {
+                if !html.is_empty() {
+                    html.push_str(line_break);
+                }
+                html.push_str("<pre>");
+            }
+            code_block = !code_block;
+            continue;
+        }
+        if code_block {
+            if !html.ends_with("<pre>") {
+                html.push('\n');
+            }
+            html.push_str(&escape(line));
+            continue;
+        }
+
+        if !html.is_empty() {
+            html.push_str(line_break);
+        }
+        match line.strip_prefix("> ") {
+            Some(quote) => {
+                html.push_str("<blockquote>");
+                html.push_str(&inline_html(quote));
+                html.push_str("</blockquote>");
+            }
+            None => html.push_str(&inline_html(line)),
+        }
+    }
+    if code_block {
+        html.push_str("</pre>");
+    }
+    html
+}
+
+/// Removes the markdown, for clients that only show plain text.
+pub fn to_plain(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.starts_with("```"))
+        .map(|line| line.replace("**", "").replace('`', ""))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Renders bold text and inline code within a line.
+fn inline_html(line: &str) -> String {
+    let mut html = String::new();
+    let (mut bold, mut code) = (false, false);
+    let mut chars = line.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '`' => {
+                html.push_str(if code { "</code>" } else { "<code>" });
+                code = !code;
+            }
+            '*' if !code && chars.peek() == Some(&'*') => {
+                chars.next();
+                html.push_str(if bold { "</b>" } else { "<b>" });
+                bold = !bold;
+            }
+            _ => html.push_str(&escape(&char.to_string())),
+        }
+    }
+    // unbalanced markers would otherwise leave the HTML invalid
+    if code {
+        html.push_str("</code>");
+    }
+    if bold {
+        html.push_str("</b>");
+    }
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_renders_bold_code_quotes_and_blocks() {
+        let text = "**Summary**\n> Used `5 kWh`\n```\n00h ██ 1.00\n01h █  0.50\n```\nDone";
+        assert_eq!(
+            to_html(text, "<br>"),
+            "<b>Summary</b><br><blockquote>Used <code>5 kWh</code></blockquote><br>\
+             <pre>00h ██ 1.00\n01h █  0.50</pre><br>Done"
+        );
+    }
+
+    #[test]
+    fn nested_markers_stay_nested() {
+        assert_eq!(
+            to_html("**total `5 kWh` today**", "\n"),
+            "<b>total <code>5 kWh</code> today</b>"
+        );
+        // inside code, asterisks are just text
+        assert_eq!(to_html("`a**b`", "\n"), "<code>a**b</code>");
+        assert_eq!(to_html("2 * 3 kWh", "\n"), "2 * 3 kWh");
+    }
+
+    #[test]
+    fn unbalanced_markers_are_closed() {
+        assert_eq!(to_html("**open", "\n"), "<b>open</b>");
+        assert_eq!(to_html("`open", "\n"), "<code>open</code>");
+        assert_eq!(
+            to_html("**bold `code** end", "\n"),
+            "<b>bold <code>code** end</code></b>"
+        );
+        assert_eq!(to_html("```\nno end", "\n"), "<pre>no end</pre>");
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let name = "Fridge <Kitchen> & Co";
+        assert_eq!(
+            to_html(&format!("**{}**", name), "\n"),
+            "<b>Fridge &lt;Kitchen&gt; &amp; Co</b>"
+        );
+        assert_eq!(
+            to_html(&format!("```\n{}\n```", name), "\n"),
+            "<pre>Fridge &lt;Kitchen&gt; &amp; Co</pre>"
+        );
+        // already escaped text is shown as it was written
+        assert_eq!(to_html("&lt;", "\n"), "&amp;lt;");
+    }
+
+    #[test]
+    fn plain_text_has_no_markers() {
+        assert_eq!(
+            to_plain("**Fridge <Kitchen>** uses `1 kWh`\n```\n00h ██\n```\n**open"),
+            "Fridge <Kitchen> uses 1 kWh\n00h ██\nopen"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{
+    commands::{self, Answer},
+    db::Db,
+    markup,
+    scheduler::Scheduler,
+};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use reqwest::{Client, Url};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// How long a sync waits for new events, in milliseconds.
+const SYNC_TIMEOUT: u64 = 30_000;
+
+/// Answers text-commands in the room `MATRIX_ROOM_ID`, using the
+/// [client-server API](https://spec.matrix.org/latest/client-server-api/) of `MATRIX_HOMESERVER`.
+#[derive(Clone)]
+struct Matrix {
+    client: Client,
+    homeserver: Url,
+    access_token: String,
+    /// The users from `MATRIX_CONFIG_USERS`, who may change the config.
+    config_users: HashSet<String>,
+}
+
+#[derive(Deserialize)]
+struct WhoAmI {
+    user_id: String,
+}
+
+#[derive(Deserialize)]
+struct Joined {
+    room_id: String,
+}
+
+#[derive(Deserialize)]
+struct Uploaded {
+    content_uri: String,
+}
+
+#[derive(Deserialize)]
+struct Sync {
+    next_batch: String,
+    #[serde(default)]
+    rooms: Rooms,
+}
+
+#[derive(Deserialize, Default)]
+struct Rooms {
+    #[serde(default)]
+    join: HashMap<String, JoinedRoom>,
+}
+
+#[derive(Deserialize)]
+struct JoinedRoom {
+    timeline: Timeline,
+}
+
+#[derive(Deserialize)]
+struct Timeline {
+    #[serde(default)]
+    events: Vec<Event>,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    sender: String,
+    #[serde(default)]
+    content: serde_json::Value,
+}
+
+/// Answers commands until the app is stopped.
+pub async fn start_bot(db: Db, scheduler: Scheduler) -> Result<()> {
+    let matrix = Matrix::from_env()?;
+    let room = env::var("MATRIX_ROOM_ID").map_err(|_| anyhow!("MATRIX_ROOM_ID is not set."))?;
+    let user_id = matrix.whoami().await?;
+    // also resolves aliases like #powerfox:example.com
+    let room_id = matrix.join(&room).await?;
+    let filter = json!({ "room": { "rooms": [room_id] } }).to_string();
+
+    // skip the messages sent before the start
+    let mut since = matrix.sync(&filter, None, 0).await?.next_batch;
+    info!(
+        "Answering commands in Matrix-room {} as {}.",
+        room_id, user_id
+    );
+    loop {
+        let sync = match matrix.sync(&filter, Some(&since), SYNC_TIMEOUT).await {
+            Ok(sync) => sync,
+            Err(err) => {
+                warn!("Could not sync with Matrix: {}", err);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+        since = sync.next_batch;
+
+        let Some(room) = sync.rooms.join.get(&room_id) else {
+            continue;
+        };
+        for event in &room.timeline.events {
+            let Some(text) = event.content["body"].as_str() else {
+                continue;
+            };
+            if event.kind != "m.room.message" || event.sender == user_id {
+                continue;
+            }
+            // commands can take a while, so they shouldn't block the following ones
+            let (matrix, db, scheduler) = (matrix.clone(), db.clone(), scheduler.clone());
+            let (room_id, sender, text) = (room_id.clone(), event.sender.clone(), text.to_string());
+            tokio::spawn(async move {
+                matrix
+                    .answer(&db, &scheduler, &room_id, &sender, &text)
+                    .await
+            });
+        }
+    }
+}
+
+/// Checks the access-token, returning the bot's user-ID.
+pub async fn check() -> Result<String> {
+    let user_id = Matrix::from_env()?.whoami().await?;
+    Ok(format!("logged in as {}", user_id))
+}
+
+impl Matrix {
+    fn from_env() -> Result<Self> {
+        let homeserver =
+            env::var("MATRIX_HOMESERVER").map_err(|_| anyhow!("MATRIX_HOMESERVER is not set."))?;
+        let access_token = env::var("MATRIX_ACCESS_TOKEN")
+            .map_err(|_| anyhow!("MATRIX_ACCESS_TOKEN is not set."))?;
+        let config_users = env::var("MATRIX_CONFIG_USERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|user| !user.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Ok(Matrix {
+            client: Client::new(),
+            homeserver: Url::parse(&homeserver)?,
+            access_token,
+            config_users,
+        })
+    }
+
+    async fn answer(
+        &self,
+        db: &Db,
+        scheduler: &Scheduler,
+        room_id: &str,
+        sender: &str,
+        text: &str,
+    ) {
+        let may_configure = self.config_users.contains(sender);
+        let Some(answer) = commands::run_text(db, scheduler, text, sender, may_configure).await
+        else {
+            return;
+        };
+        if let Err(err) = self.send_answer(room_id, answer).await {
+            warn!("Could not answer in Matrix-room {}: {}", room_id, err);
+        }
+    }
+
+    async fn whoami(&self) -> Result<String> {
+        let url = self.url("client", &["account", "whoami"])?;
+        let whoami: WhoAmI = self.send(self.client.get(url)).await?;
+        Ok(whoami.user_id)
+    }
+
+    /// Joins the room, which does nothing if the bot already is a member, and returns its ID.
+    async fn join(&self, room: &str) -> Result<String> {
+        let url = self.url("client", &["join", room])?;
+        let joined: Joined = self.send(self.client.post(url).json(&json!({}))).await?;
+        Ok(joined.room_id)
+    }
+
+    async fn sync(&self, filter: &str, since: Option<&str>, timeout: u64) -> Result<Sync> {
+        let mut url = self.url("client", &["sync"])?;
+        url.query_pairs_mut()
+            .append_pair("filter", filter)
+            .append_pair("timeout", &timeout.to_string());
+        if let Some(since) = since {
+            url.query_pairs_mut().append_pair("since", since);
+        }
+        let request = self
+            .client
+            .get(url)
+            .timeout(Duration::from_millis(timeout + 10_000));
+        self.send(request).await
+    }
+
+    /// Sends the answer with its text as HTML, followed by its file.
+    async fn send_answer(&self, room_id: &str, answer: Answer) -> Result<()> {
+        self.send_event(
+            room_id,
+            json!({
+                "msgtype": "m.text",
+                "body": markup::to_plain(&answer.text),
+                "format": "org.matrix.custom.html",
+                "formatted_body": markup::to_html(&answer.text, "<br>"),
+            }),
+        )
+        .await?;
+        if let Some(file) = answer.file {
+            let mut url = self.url("media", &["upload"])?;
+            url.query_pairs_mut().append_pair("filename", &file.name);
+            let size = file.data.len();
+            let request = self
+                .client
+                .post(url)
+                .header("Content-Type", "application/octet-stream")
+                .body(file.data);
+            let uploaded: Uploaded = self.send(request).await?;
+            self.send_event(
+                room_id,
+                json!({
+                    "msgtype": "m.file",
+                    "body": file.name,
+                    "url": uploaded.content_uri,
+                    "info": { "size": size },
+                }),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn send_event(&self, room_id: &str, content: serde_json::Value) -> Result<()> {
+        let txn_id = Uuid::new_v4().to_string();
+        let url = self.url(
+            "client",
+            &["rooms", room_id, "send", "m.room.message", &txn_id],
+        )?;
+        let _: serde_json::Value = self.send(self.client.put(url).json(&content)).await?;
+        Ok(())
+    }
+
+    /// The URL of the endpoint of the given API, e.g. client or media, with each segment encoded.
+    fn url(&self, api: &str, segments: &[&str]) -> Result<Url> {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow!("Invalid MATRIX_HOMESERVER {}.", self.homeserver))?
+            .pop_if_empty()
+            .extend(["_matrix", api, "v3"])
+            .extend(segments);
+        Ok(url)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<T> {
+        Ok(request
+            .bearer_auth(&self.access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
@@ -0,0 +1,330 @@
+use crate::db::{
+    Aggregate, Config, ConfigHistory, CreateDay, Day, Days, Device, Granularity, Hour, JobRun,
+    Peak, Storage, Trigger,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use log::info;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+/// [Storage] that only keeps data in memory, e.g. for tests or trying out the bot.
+/// Starts with the same default config as a fresh database.
+pub struct Memory {
+    days: Mutex<BTreeMap<NaiveDate, Day>>,
+    hours: Mutex<BTreeMap<DateTime<Utc>, Hour>>,
+    peaks: Mutex<BTreeMap<NaiveDate, Peak>>,
+    configs: Mutex<BTreeMap<NaiveDate, Config>>,
+    schedules: Mutex<HashMap<String, String>>,
+    devices: Mutex<BTreeMap<String, Device>>,
+    job_runs: Mutex<Vec<JobRun>>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        let config = Config {
+            valid_from: NaiveDate::default(),
+            cost_heating: 0.23,
+            cost_general: 0.3,
+            monthly_budget_heating: 460.0,
+            monthly_budget_general: 50.0,
+            base_fee_heating: 0.0,
+            base_fee_general: 0.0,
+        };
+
+        info!("Set up in-memory storage, data is lost on exit.");
+        Memory {
+            days: Mutex::new(BTreeMap::new()),
+            hours: Mutex::new(BTreeMap::new()),
+            peaks: Mutex::new(BTreeMap::new()),
+            configs: Mutex::new(BTreeMap::from([(config.valid_from, config)])),
+            schedules: Mutex::new(HashMap::new()),
+            devices: Mutex::new(BTreeMap::new()),
+            job_runs: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Locks the mutex, turning a poisoned lock into an error.
+fn lock<T>(mutex: &Mutex<T>) -> Result<std::sync::MutexGuard<'_, T>> {
+    mutex
+        .lock()
+        .map_err(|_| anyhow!("In-memory storage is poisoned."))
+}
+
+#[async_trait]
+impl Storage for Memory {
+    async fn save_days(&self, days: Vec<CreateDay>) -> Result<()> {
+        // holding the lock makes the days appear at once
+        let mut stored = lock(&self.days)?;
+        for day in days {
+            stored.insert(day.date, Day::from(day));
+        }
+        Ok(())
+    }
+
+    async fn save_import(&self, day: CreateDay, hours: Vec<Hour>, peak: Peak) -> Result<Day> {
+        let (mut days, mut stored_hours, mut peaks) =
+            (lock(&self.days)?, lock(&self.hours)?, lock(&self.peaks)?);
+        let day = Day::from(day);
+        days.insert(day.date, day.clone());
+        for hour in hours {
+            stored_hours.insert(hour.timestamp, hour);
+        }
+        peaks.insert(peak.date, peak);
+        Ok(day)
+    }
+
+    async fn get_day(&self, date: NaiveDate) -> Result<Option<Day>> {
+        Ok(lock(&self.days)?.get(&date).cloned())
+    }
+
+    async fn get_latest_day(&self) -> Result<Option<Day>> {
+        Ok(lock(&self.days)?.values().next_back().cloned())
+    }
+
+    async fn get_days_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Days> {
+        let days = lock(&self.days)?
+            .range(from..=to)
+            .map(|(_, day)| day.clone())
+            .collect();
+        Ok(Days(days))
+    }
+
+    async fn get_hours_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Hour>> {
+        Ok(lock(&self.hours)?
+            .values()
+            .filter(|hour| hour.date >= from && hour.date <= to)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_peaks_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Peak>> {
+        Ok(lock(&self.peaks)?
+            .range(from..=to)
+            .map(|(_, peak)| peak.clone())
+            .collect())
+    }
+
+    async fn aggregate(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        granularity: Granularity,
+    ) -> Result<Vec<Aggregate>> {
+        let configs = self.get_config_history().await?;
+        let mut periods: BTreeMap<NaiveDate, Vec<Day>> = BTreeMap::new();
+        for day in self.get_days_between(from, to).await?.0 {
+            periods
+                .entry(granularity.period_of(day.date))
+                .or_default()
+                .push(day);
+        }
+
+        let mut aggregates = Vec::new();
+        for (period, days) in periods {
+            let count = days.len() as f64;
+            let heating = days.iter().map(|day| day.heating_consumption);
+            let general = days.iter().map(|day| day.general_consumption);
+            let mut heating_cost = 0.0;
+            let mut general_cost = 0.0;
+            for day in &days {
+                let config = configs.at(day.date)?;
+                heating_cost += day.heating_cost(config);
+                general_cost += day.general_cost(config);
+            }
+
+            aggregates.push(Aggregate {
+                period,
+                days: days.len() as i64,
+                heating_sum: heating.clone().sum(),
+                heating_avg: heating.clone().sum::<f64>() / count,
+                heating_min: heating.clone().fold(f64::INFINITY, f64::min),
+                heating_max: heating.fold(f64::NEG_INFINITY, f64::max),
+                general_sum: general.clone().sum(),
+                general_avg: general.clone().sum::<f64>() / count,
+                general_min: general.clone().fold(f64::INFINITY, f64::min),
+                general_max: general.fold(f64::NEG_INFINITY, f64::max),
+                average_temperature: days.iter().map(|day| day.average_temperature).sum::<f64>()
+                    / count,
+                heating_cost,
+                general_cost,
+            });
+        }
+        Ok(aggregates)
+    }
+
+    async fn get_config_history(&self) -> Result<ConfigHistory> {
+        Ok(ConfigHistory(
+            lock(&self.configs)?.values().cloned().collect(),
+        ))
+    }
+
+    async fn save_config(&self, config: Config) -> Result<Config> {
+        lock(&self.configs)?.insert(config.valid_from, config.clone());
+        Ok(config)
+    }
+
+    async fn get_schedule(&self, name: &str) -> Result<Option<String>> {
+        Ok(lock(&self.schedules)?.get(name).cloned())
+    }
+
+    async fn save_schedule(&self, name: &str, cron: &str) -> Result<()> {
+        lock(&self.schedules)?.insert(name.to_string(), cron.to_string());
+        Ok(())
+    }
+
+    async fn get_devices(&self) -> Result<Vec<Device>> {
+        let mut devices: Vec<Device> = lock(&self.devices)?.values().cloned().collect();
+        devices.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(devices)
+    }
+
+    async fn save_devices(&self, devices: Vec<Device>) -> Result<()> {
+        let mut stored = lock(&self.devices)?;
+        for device in devices {
+            stored.insert(device.device_id.clone(), device);
+        }
+        Ok(())
+    }
+
+    async fn start_job_run(&self, job: &str, trigger: Trigger) -> Result<i64> {
+        let mut runs = lock(&self.job_runs)?;
+        let id = runs.len() as i64 + 1;
+        runs.push(JobRun {
+            id,
+            job: job.to_string(),
+            triggered_by: trigger.as_str().to_string(),
+            started_at: Utc::now(),
+            finished_at: None,
+            status: "running".to_string(),
+            error: None,
+            days: String::new(),
+        });
+        Ok(id)
+    }
+
+    async fn finish_job_run(
+        &self,
+        id: i64,
+        error: Option<String>,
+        days: Vec<NaiveDate>,
+    ) -> Result<()> {
+        let mut runs = lock(&self.job_runs)?;
+        if let Some(run) = runs.iter_mut().find(|run| run.id == id) {
+            run.finished_at = Some(Utc::now());
+            run.status = JobRun::status_of(&error).to_string();
+            run.error = error;
+            run.days = JobRun::join_days(&days);
+        }
+        Ok(())
+    }
+
+    async fn get_job_runs(&self, limit: i64) -> Result<Vec<JobRun>> {
+        Ok(lock(&self.job_runs)?
+            .iter()
+            .rev()
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    fn day(date: NaiveDate, heating_consumption: f64, general_consumption: f64) -> CreateDay {
+        CreateDay {
+            heating_consumption,
+            general_consumption,
+            average_temperature: 10.0,
+            date,
+        }
+    }
+
+    #[tokio::test]
+    async fn aggregate_per_month_with_the_config_of_each_day() {
+        let db = Memory::new();
+        let config = db.get_config_at(date(1, 1)).await.unwrap();
+        db.save_config(Config {
+            cost_heating: 1.0,
+            cost_general: 1.0,
+            ..config.clone().with_valid_from(date(1, 1))
+        })
+        .await
+        .unwrap();
+        // the price doubles in the middle of February
+        db.save_config(Config {
+            cost_heating: 2.0,
+            cost_general: 2.0,
+            ..config.with_valid_from(date(2, 15))
+        })
+        .await
+        .unwrap();
+        db.save_days(vec![
+            day(date(1, 31), 4.0, 1.0),
+            day(date(2, 1), 2.0, 3.0),
+            day(date(2, 20), 6.0, 5.0),
+            // outside of the range
+            day(date(3, 1), 100.0, 100.0),
+        ])
+        .await
+        .unwrap();
+
+        let aggregates = db
+            .aggregate(date(1, 1), date(2, 28), Granularity::Month)
+            .await
+            .unwrap();
+        assert_eq!(aggregates.len(), 2);
+        let (january, february) = (&aggregates[0], &aggregates[1]);
+        assert_eq!(january.period, date(1, 1));
+        assert_eq!(january.days, 1);
+        assert_eq!(january.heating_cost, 4.0);
+        assert_eq!(february.period, date(2, 1));
+        assert_eq!(february.days, 2);
+        assert_eq!(february.heating_sum, 8.0);
+        assert_eq!(february.heating_avg, 4.0);
+        assert_eq!(february.heating_min, 2.0);
+        assert_eq!(february.heating_max, 6.0);
+        assert_eq!(february.general_sum, 8.0);
+        assert_eq!(february.heating_cost, 2.0 + 12.0);
+        assert_eq!(february.general_cost, 3.0 + 10.0);
+    }
+
+    #[tokio::test]
+    async fn aggregate_per_week_starts_on_monday() {
+        let db = Memory::new();
+        // Sunday, Monday and Tuesday
+        db.save_days(vec![
+            day(date(3, 8), 1.0, 1.0),
+            day(date(3, 9), 2.0, 2.0),
+            day(date(3, 10), 3.0, 3.0),
+        ])
+        .await
+        .unwrap();
+
+        let aggregates = db
+            .aggregate(date(3, 1), date(3, 31), Granularity::Week)
+            .await
+            .unwrap();
+        let weeks: Vec<(NaiveDate, i64)> = aggregates
+            .iter()
+            .map(|aggregate| (aggregate.period, aggregate.days))
+            .collect();
+        assert_eq!(weeks, vec![(date(3, 2), 1), (date(3, 9), 2)]);
+    }
+}
@@ -1,12 +1,20 @@
-use crate::util::deserialize_datetime;
+use crate::{
+    metrics,
+    util::{self, deserialize_datetime},
+};
 use anyhow::{anyhow, Result};
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use reqwest::{Client, StatusCode};
-use serde::{Deserialize, Serialize};
-use std::env;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeMap, env};
+
+/// The API for historical weather-data, see [Meteo::get_daily_temperatures].
+const DEFAULT_ARCHIVE_URL: &str = "https://archive-api.open-meteo.com";
+
 pub struct Meteo {
     client: Client,
     base_url: String,
+    archive_url: String,
     latitude: String,
     longitude: String,
 }
@@ -16,54 +24,69 @@ impl Meteo {
         Ok(Meteo {
             client: Client::new(),
             base_url: env::var("WEATHER_BASE_URL")?,
+            archive_url: env::var("WEATHER_ARCHIVE_URL").unwrap_or(DEFAULT_ARCHIVE_URL.to_string()),
             // TODO check if parsing here makes sense, Strings might suffice
             latitude: env::var("WEATHER_LATITUDE")?,
             longitude: env::var("WEATHER_LONGITUDE")?,
         })
     }
 
-    /// Gets the temperature for this day from 00:00 to 23:00.
+    /// Gets the temperature for the given date from 00:00 to 23:00 in the configured timezone.
+    /// The forecast only reaches back a few months, see [Meteo::get_daily_temperatures] for older dates.
     ///
-    /// See [this URL](https://api.open-meteo.com/v1/forecast?latitude=51.28&longitude=8.87&hourly=temperature_2m&forecast_days=1&past_days=1) for more information.
-    pub async fn get_temperature_for_yesterday(&self) -> Result<TemperatureData> {
-        let response = self
-            .client
-            .get(format!(
-                "{}/v1/forecast?latitude={}&longitude={}&hourly=temperature_2m&forecast_days=1&past_days=1",
-                &self.base_url, &self.latitude, &self.longitude
-            ))
-            .send()
-            .await?;
-        if response.status() != StatusCode::OK {
-            Err(anyhow!(
-                "Status-Code of response was not OK: {}",
-                response.text().await?
-            ))
-        } else {
-            Ok(response.json().await?)
-        }
+    /// See [this URL](https://api.open-meteo.com/v1/forecast?latitude=51.28&longitude=8.87&hourly=temperature_2m&start_date=2024-01-01&end_date=2024-01-01&timezone=Europe/Berlin) for more information.
+    pub async fn get_temperature_for(&self, date: NaiveDate) -> Result<TemperatureData> {
+        self.get(format!(
+            "{}/v1/forecast?latitude={}&longitude={}&hourly=temperature_2m&start_date={}&end_date={}&timezone={}",
+            &self.base_url, &self.latitude, &self.longitude, date, date, util::timezone().name()
+        ))
+        .await
     }
 
-    /// Gets the temperature for this day from 00:00 to 23:00.
+    /// Gets the temperature for this day from 00:00 to 23:00 in the configured timezone.
     ///
-    /// See [this URL](https://api.open-meteo.com/v1/forecast?latitude=51.28&longitude=8.87&hourly=temperature_2m&forecast_days=1) for more information.
+    /// See [this URL](https://api.open-meteo.com/v1/forecast?latitude=51.28&longitude=8.87&hourly=temperature_2m&forecast_days=1&timezone=Europe/Berlin) for more information.
     pub async fn get_temperature_for_today(&self) -> Result<TemperatureData> {
-        let response = self
-            .client
+        self.get(format!(
+            "{}/v1/forecast?latitude={}&longitude={}&hourly=temperature_2m&forecast_days=1&timezone={}",
+            &self.base_url, &self.latitude, &self.longitude, util::timezone().name()
+        ))
+        .await
+    }
+
+    /// Gets the average temperature of each day between both dates (including both) in the configured timezone.
+    /// This uses the archive, which only has data up to a few days ago, but reaches back for decades.
+    /// Later days are missing, so they need to be fetched with [Meteo::get_temperature_for].
+    ///
+    /// See [this URL](https://archive-api.open-meteo.com/v1/archive?latitude=51.28&longitude=8.87&hourly=temperature_2m&start_date=2023-01-01&end_date=2023-01-31&timezone=Europe/Berlin) for more information.
+    pub async fn get_daily_temperatures(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<BTreeMap<NaiveDate, f64>> {
+        let data: TemperatureData = self
             .get(format!(
-                "{}/v1/forecast?latitude={}&longitude={}&hourly=temperature_2m&forecast_days=1",
-                &self.base_url, &self.latitude, &self.longitude
+                "{}/v1/archive?latitude={}&longitude={}&hourly=temperature_2m&start_date={}&end_date={}&timezone={}",
+                &self.archive_url, &self.latitude, &self.longitude, from, to, util::timezone().name()
             ))
-            .send()
             .await?;
-        if response.status() != StatusCode::OK {
-            Err(anyhow!(
-                "Status-Code of response was not OK: {}",
-                response.text().await?
-            ))
-        } else {
-            Ok(response.json().await?)
-        }
+        Ok(data.daily_average_temperatures())
+    }
+
+    /// Sends a request to the given URL, which is counted and timed in the [metrics].
+    async fn get<T: DeserializeOwned>(&self, url: String) -> Result<T> {
+        metrics::observe_request(metrics::METEO, async {
+            let response = self.client.get(url).send().await?;
+            if response.status() != StatusCode::OK {
+                Err(anyhow!(
+                    "Status-Code of response was not OK: {}",
+                    response.text().await?
+                ))
+            } else {
+                Ok(response.json().await?)
+            }
+        })
+        .await
     }
 }
 
@@ -82,23 +105,42 @@ pub struct TemperatureData {
 }
 
 impl TemperatureData {
-    /// Calculate the average temperature.
+    /// Calculate the average temperature, skipping hours without one.
     pub fn average_temperature(&self) -> Result<f64> {
-        match self
+        let temperatures: Vec<f64> = self
             .hourly
             .temperature_2m
             .iter()
-            // NOTE I don't know whether we should copy here
+            .flatten()
             .copied()
-            .reduce(|a, b| a + b)
-        {
-            Some(sum) => Ok(sum / self.hourly.temperature_2m.len() as f64),
+            .collect();
+        match temperatures.iter().copied().reduce(|a, b| a + b) {
+            Some(sum) => Ok(sum / temperatures.len() as f64),
             None => Err(anyhow!(
                 "Could not reduce data: {:#?}",
                 self.hourly.temperature_2m
             )),
         }
     }
+
+    /// Calculate the average temperature of each day, skipping hours without one.
+    /// Days without any temperature are missing.
+    pub fn daily_average_temperatures(&self) -> BTreeMap<NaiveDate, f64> {
+        let mut days: BTreeMap<NaiveDate, Vec<f64>> = BTreeMap::new();
+        for (time, temperature) in self.hourly.time.iter().zip(&self.hourly.temperature_2m) {
+            if let Some(temperature) = temperature {
+                days.entry(time.date()).or_default().push(*temperature);
+            }
+        }
+        days.into_iter()
+            .map(|(date, temperatures)| {
+                (
+                    date,
+                    temperatures.iter().sum::<f64>() / temperatures.len() as f64,
+                )
+            })
+            .collect()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -112,6 +154,50 @@ pub struct Hourly {
     #[serde(deserialize_with = "deserialize_datetime")]
     time: Vec<NaiveDateTime>,
 
-    /// The temperature two meters above ground.
-    temperature_2m: Vec<f64>,
+    /// The temperature two meters above ground, missing for the last few days in the archive.
+    temperature_2m: Vec<Option<f64>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The archive's answer for two days, of which the second one isn't archived yet.
+    fn archive(second_day: &str) -> TemperatureData {
+        let times: Vec<String> = ["2026-10-16", "2026-10-17"]
+            .iter()
+            .flat_map(|date| (0..24).map(move |hour| format!("\"{}T{:02}:00\"", date, hour)))
+            .collect();
+        let first_day = ["10.0"; 12].join(",") + "," + &["14.0"; 12].join(",");
+        serde_json::from_str(&format!(
+            r#"{{"latitude": 51.28, "longitude": 8.87, "generationtime_ms": 0.1, "utc_offset_seconds": 7200,
+            "timezone": "Europe/Berlin", "timezone_abbreviation": "CEST", "elevation": 200.0,
+            "hourly_units": {{"time": "iso8601", "temperature_2m": "°C"}},
+            "hourly": {{"time": [{}], "temperature_2m": [{},{}]}}}}"#,
+            times.join(","),
+            first_day,
+            second_day
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn days_without_temperatures_are_missing() {
+        let data = archive(&["null"; 24].join(","));
+        assert_eq!(
+            data.daily_average_temperatures(),
+            BTreeMap::from([(NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(), 12.0)])
+        );
+    }
+
+    #[test]
+    fn hours_without_temperatures_are_skipped() {
+        let data = archive(&(["8.0"; 6].join(",") + "," + &["null"; 18].join(",")));
+        let days = data.daily_average_temperatures();
+        assert_eq!(days[&NaiveDate::from_ymd_opt(2026, 10, 17).unwrap()], 8.0);
+        assert_eq!(
+            data.average_temperature().unwrap(),
+            (12.0 * 24.0 + 8.0 * 6.0) / 30.0
+        );
+    }
 }
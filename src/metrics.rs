@@ -0,0 +1,160 @@
+use crate::{
+    db::{Db, Granularity},
+    scheduler::DAILY,
+    util,
+};
+use anyhow::Result;
+use chrono::Utc;
+use prometheus::{
+    register_gauge, register_gauge_vec, register_histogram_vec, register_int_counter_vec, Encoder,
+    Gauge, GaugeVec, HistogramVec, IntCounterVec, TextEncoder,
+};
+use std::{future::Future, sync::LazyLock, time::Instant};
+
+/// The label of requests to the Powerfox-API.
+pub const POWERFOX: &str = "powerfox";
+
+/// The label of requests to the weather-API.
+pub const METEO: &str = "meteo";
+
+static UPSTREAM_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "powerfox_upstream_requests_total",
+        "Requests to the Powerfox- and weather-API by outcome.",
+        &["api", "outcome"]
+    )
+    .expect("Could not register metric.")
+});
+
+static UPSTREAM_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "powerfox_upstream_request_duration_seconds",
+        "Duration of requests to the Powerfox- and weather-API.",
+        &["api"]
+    )
+    .expect("Could not register metric.")
+});
+
+static JOB_RUNS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "powerfox_job_runs_total",
+        "Runs of the scheduled jobs by outcome.",
+        &["job", "outcome"]
+    )
+    .expect("Could not register metric.")
+});
+
+static LAST_IMPORT: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "powerfox_last_import_timestamp_seconds",
+        "When a day was last imported from Powerfox since the app started, as UNIX-timestamp."
+    )
+    .expect("Could not register metric.")
+});
+
+static LATEST_DAY: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "powerfox_latest_day_timestamp_seconds",
+        "The start of the most recent stored day, as UNIX-timestamp."
+    )
+    .expect("Could not register metric.")
+});
+
+static LATEST_CONSUMPTION: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "powerfox_latest_day_consumption_kwh",
+        "The consumption of the most recent stored day per meter.",
+        &["meter"]
+    )
+    .expect("Could not register metric.")
+});
+
+static MONTH_COST: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "powerfox_month_cost_euros",
+        "The cost of the current month so far per meter, including base fees.",
+        &["meter"]
+    )
+    .expect("Could not register metric.")
+});
+
+static MONTH_BUDGET: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "powerfox_month_budget_euros",
+        "The budget of the current month per meter.",
+        &["meter"]
+    )
+    .expect("Could not register metric.")
+});
+
+/// Awaits the request, counting it by outcome and timing it.
+pub async fn observe_request<T>(api: &str, request: impl Future<Output = Result<T>>) -> Result<T> {
+    let start = Instant::now();
+    let result = request.await;
+    UPSTREAM_DURATION
+        .with_label_values(&[api])
+        .observe(start.elapsed().as_secs_f64());
+    UPSTREAM_REQUESTS
+        .with_label_values(&[api, outcome(result.is_ok())])
+        .inc();
+    result
+}
+
+/// Counts a run of the given job.
+pub fn record_job(job: &str, success: bool) {
+    JOB_RUNS.with_label_values(&[job, outcome(success)]).inc();
+}
+
+/// Remembers that a day was just imported.
+pub fn record_import() {
+    LAST_IMPORT.set(Utc::now().timestamp() as f64);
+}
+
+fn outcome(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "error"
+    }
+}
+
+/// Updates the metrics taken from the database and encodes all metrics in Prometheus' text-format.
+pub async fn render(db: &Db) -> Result<String> {
+    // make sure these are exported before the first import or run
+    LazyLock::force(&LAST_IMPORT);
+    for success in [true, false] {
+        JOB_RUNS.with_label_values(&[DAILY, outcome(success)]);
+    }
+
+    if let Some(day) = db.get_latest_day().await? {
+        LATEST_DAY.set(util::start_of_day(day.date).timestamp() as f64);
+        LATEST_CONSUMPTION
+            .with_label_values(&["heating"])
+            .set(day.heating_consumption);
+        LATEST_CONSUMPTION
+            .with_label_values(&["general"])
+            .set(day.general_consumption);
+    }
+
+    let today = util::today();
+    let month = db
+        .aggregate(util::first_of_month(today), today, Granularity::Month)
+        .await?;
+    let config = db.get_config().await?;
+    MONTH_COST
+        .with_label_values(&["heating"])
+        .set(month.first().map_or(0.0, |month| month.heating_cost));
+    MONTH_COST
+        .with_label_values(&["general"])
+        .set(month.first().map_or(0.0, |month| month.general_cost));
+    MONTH_BUDGET
+        .with_label_values(&["heating"])
+        .set(config.monthly_budget_heating);
+    MONTH_BUDGET
+        .with_label_values(&["general"])
+        .set(config.monthly_budget_general);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
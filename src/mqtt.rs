@@ -0,0 +1,523 @@
+use crate::{
+    db::{Aggregate, Config, Day, Db, Granularity},
+    util,
+};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use log::{info, warn};
+use rumqttc::{AsyncClient, EventLoop, MqttOptions, QoS};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{env, sync::OnceLock, time::Duration};
+
+static MQTT: OnceLock<Mqtt> = OnceLock::new();
+
+/// The topic below which the states are published, if `MQTT_TOPIC` isn't set.
+const DEFAULT_TOPIC: &str = "powerfox";
+
+/// The prefix Home Assistant listens to for discovery, if `MQTT_DISCOVERY_PREFIX` isn't set.
+const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+
+/// How many messages are queued while the broker is unreachable - further messages are dropped.
+const QUEUE_SIZE: usize = 64;
+
+/// Publishes days to an MQTT-broker, including the config for Home Assistant's MQTT discovery.
+struct Mqtt {
+    client: AsyncClient,
+    topic: String,
+}
+
+/// The state of yesterday or today, published as JSON.
+#[derive(Serialize)]
+struct DayState {
+    date: NaiveDate,
+    /// The start of the day, from which Home Assistant counts the energy, see [sensor_config].
+    last_reset: String,
+    heating_consumption: f64,
+    general_consumption: f64,
+    heating_cost: f64,
+    general_cost: f64,
+    average_temperature: f64,
+}
+
+/// The state of the current month, published as JSON.
+#[derive(Serialize)]
+struct MonthState {
+    heating_cost: f64,
+    general_cost: f64,
+    heating_budget: f64,
+    general_budget: f64,
+    /// The share of the budget used so far, in percent.
+    heating_budget_used: f64,
+    general_budget_used: f64,
+}
+
+/// Connects to the broker at `MQTT_HOST`, if it's set - otherwise, nothing is published.
+/// This needs to be called once at startup, before anything can be published.
+pub async fn init() -> Result<()> {
+    let Ok(host) = env::var("MQTT_HOST") else {
+        info!("MQTT_HOST is not set, so nothing is published via MQTT.");
+        return Ok(());
+    };
+    let port: u16 = match env::var("MQTT_PORT") {
+        Ok(port) => port.parse()?,
+        Err(_) => 1883,
+    };
+    let client_id = env::var("MQTT_CLIENT_ID").unwrap_or("powerfox".to_string());
+    let topic = env::var("MQTT_TOPIC").unwrap_or(DEFAULT_TOPIC.to_string());
+    let discovery_prefix =
+        env::var("MQTT_DISCOVERY_PREFIX").unwrap_or(DEFAULT_DISCOVERY_PREFIX.to_string());
+
+    let mut options = MqttOptions::new(client_id, &host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if let Ok(username) = env::var("MQTT_USERNAME") {
+        options.set_credentials(username, env::var("MQTT_PASSWORD").unwrap_or_default());
+    }
+
+    let (client, event_loop) = AsyncClient::new(options, QUEUE_SIZE);
+    tokio::spawn(poll(event_loop));
+
+    let mqtt = Mqtt { client, topic };
+    // retained, so Home Assistant picks the sensors up whenever it (re-)starts
+    mqtt.publish_discovery(&discovery_prefix)?;
+    MQTT.set(mqtt)
+        .map_err(|_| anyhow!("MQTT was already initialized."))?;
+
+    info!("Publishing via MQTT to {}:{}.", host, port);
+    Ok(())
+}
+
+/// Drives the connection, which reconnects automatically on the next poll after an error.
+async fn poll(mut event_loop: EventLoop) {
+    loop {
+        if let Err(err) = event_loop.poll().await {
+            warn!("MQTT-connection failed: {}", err);
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    }
+}
+
+/// Publishes yesterday's data and the current month without waiting for the broker.
+/// Failures are only logged, as MQTT is optional.
+pub async fn publish_yesterday(db: &Db, day: &Day) {
+    publish(db, "yesterday", day).await;
+}
+
+/// Publishes today's data up to now and the current month without waiting for the broker.
+/// Failures are only logged, as MQTT is optional.
+pub async fn publish_today(db: &Db, day: &Day) {
+    publish(db, "today", day).await;
+}
+
+async fn publish(db: &Db, name: &str, day: &Day) {
+    let Some(mqtt) = MQTT.get() else {
+        return;
+    };
+    if let Err(err) = mqtt.publish_states(db, name, day).await {
+        warn!("Could not publish {} via MQTT: {}", name, err);
+    }
+}
+
+impl Mqtt {
+    async fn publish_states(&self, db: &Db, name: &str, day: &Day) -> Result<()> {
+        let config = db.get_config_at(day.date).await?;
+        self.publish_state(name, &day_state(day, &config, util::timezone()))?;
+
+        let today = util::today();
+        let month = db
+            .aggregate(util::first_of_month(today), today, Granularity::Month)
+            .await?;
+        let config = db.get_config().await?;
+        self.publish_state("month", &month_state(month.first(), &config))
+    }
+
+    fn publish_state(&self, name: &str, state: &impl Serialize) -> Result<()> {
+        self.send(state_topic(&self.topic, name), serde_json::to_vec(state)?)
+    }
+
+    /// Queues the retained message, failing instead of waiting if the queue is full.
+    /// It only drains while the broker is reachable, so waiting would block imports during an outage.
+    fn send(&self, topic: String, payload: Vec<u8>) -> Result<()> {
+        self.client
+            .try_publish(topic, QoS::AtLeastOnce, true, payload)?;
+        Ok(())
+    }
+
+    /// Publishes a sensor for every value of the states, see [discovery].
+    fn publish_discovery(&self, discovery_prefix: &str) -> Result<()> {
+        for (topic, config) in discovery(&self.topic, discovery_prefix) {
+            self.send(topic, serde_json::to_vec(&config)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// The topic of a state, e.g. `powerfox/yesterday`.
+fn state_topic(topic: &str, name: &str) -> String {
+    format!("{}/{}", topic, name)
+}
+
+/// The state of the day, with the costs at the config.
+fn day_state(day: &Day, config: &Config, timezone: Tz) -> DayState {
+    DayState {
+        date: day.date,
+        last_reset: util::start_of_day_in(day.date, timezone).to_rfc3339(),
+        heating_consumption: day.heating_consumption,
+        general_consumption: day.general_consumption,
+        heating_cost: day.heating_cost(config),
+        general_cost: day.general_cost(config),
+        average_temperature: day.average_temperature,
+    }
+}
+
+/// The state of the month so far, which has no aggregate before its first day is stored.
+fn month_state(month: Option<&Aggregate>, config: &Config) -> MonthState {
+    let heating_cost = month.map_or(0.0, |month| month.heating_cost);
+    let general_cost = month.map_or(0.0, |month| month.general_cost);
+    MonthState {
+        heating_cost,
+        general_cost,
+        heating_budget: config.monthly_budget_heating,
+        general_budget: config.monthly_budget_general,
+        heating_budget_used: share(heating_cost, config.monthly_budget_heating),
+        general_budget_used: share(general_cost, config.monthly_budget_general),
+    }
+}
+
+/// The topic and config of a sensor for every value of the states,
+/// see https://www.home-assistant.io/integrations/sensor.mqtt/.
+fn discovery(topic: &str, discovery_prefix: &str) -> Vec<(String, Value)> {
+    let mut sensors = Vec::new();
+    for state in ["yesterday", "today"] {
+        for meter in ["heating", "general"] {
+            sensors.push((state, format!("{}_consumption", meter), "kWh", "energy"));
+            sensors.push((state, format!("{}_cost", meter), "EUR", "monetary"));
+        }
+        sensors.push((
+            state,
+            "average_temperature".to_string(),
+            "°C",
+            "temperature",
+        ));
+    }
+    for meter in ["heating", "general"] {
+        sensors.push(("month", format!("{}_cost", meter), "EUR", "monetary"));
+        sensors.push(("month", format!("{}_budget", meter), "EUR", "monetary"));
+        sensors.push(("month", format!("{}_budget_used", meter), "%", ""));
+    }
+
+    sensors
+        .into_iter()
+        .map(|(state, value, unit, device_class)| {
+            (
+                format!(
+                    "{}/sensor/{}/{}_{}/config",
+                    discovery_prefix, topic, state, value
+                ),
+                sensor_config(topic, state, &value, unit, device_class),
+            )
+        })
+        .collect()
+}
+
+/// The discovery-config of the sensor for a value of a state.
+fn sensor_config(topic: &str, state: &str, value: &str, unit: &str, device_class: &str) -> Value {
+    let object_id = format!("{}_{}", state, value);
+    let mut config = json!({
+        "name": object_id.replace('_', " "),
+        "unique_id": format!("{}_{}", topic, object_id),
+        "state_topic": state_topic(topic, state),
+        "value_template": format!("{{{{ value_json.{} }}}}", value),
+        "unit_of_measurement": unit,
+        "device": {
+            "identifiers": [topic],
+            "name": "Powerfox",
+            "manufacturer": "powerfox",
+        },
+    });
+    // Home Assistant only keeps statistics of energy-sensors with a state-class.
+    // The consumption starts over every day, which `total` would count as a drop and `total_increasing`
+    // would miss whenever a day is higher than the one before, so the start of the day is published as its reset.
+    match device_class {
+        "" => {}
+        "energy" => {
+            config["device_class"] = json!(device_class);
+            config["state_class"] = json!("total");
+            config["last_reset_value_template"] = json!("{{ value_json.last_reset }}");
+        }
+        _ => config["device_class"] = json!(device_class),
+    }
+    config
+}
+
+/// The share of the budget, in percent.
+fn share(cost: f64, budget: f64) -> f64 {
+    if budget > 0.0 {
+        cost / budget * 100.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memory;
+    use chrono_tz::Europe::Berlin;
+    use rumqttc::{Event, Packet};
+    use std::{collections::BTreeMap, sync::Arc};
+
+    #[tokio::test]
+    async fn unreachable_broker_does_not_block() {
+        // nothing listens on port 1 and the event loop is never polled, so the queue never drains
+        let (client, _event_loop) =
+            AsyncClient::new(MqttOptions::new("test", "127.0.0.1", 1), QUEUE_SIZE);
+        let mqtt = Mqtt {
+            client,
+            topic: DEFAULT_TOPIC.to_string(),
+        };
+        let db: Db = Arc::new(Memory::new());
+        let day = Day {
+            heating_consumption: 10.0,
+            general_consumption: 4.0,
+            average_temperature: 5.0,
+            date: util::yesterday(),
+        };
+
+        let publishing = async {
+            mqtt.publish_discovery(DEFAULT_DISCOVERY_PREFIX).unwrap();
+            let mut results = Vec::new();
+            for _ in 0..QUEUE_SIZE {
+                results.push(mqtt.publish_states(&db, "yesterday", &day).await);
+            }
+            results
+        };
+        let results = tokio::time::timeout(Duration::from_secs(5), publishing)
+            .await
+            .expect("publishing waited for the broker");
+        // once the queue is full, publishing fails right away
+        assert!(results.first().unwrap().is_ok());
+        assert!(results.last().unwrap().is_err());
+    }
+
+    fn config() -> Config {
+        Config {
+            valid_from: NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+            cost_heating: 0.25,
+            cost_general: 0.3,
+            monthly_budget_heating: 200.0,
+            monthly_budget_general: 0.0,
+            base_fee_heating: 31.0,
+            base_fee_general: 0.0,
+        }
+    }
+
+    fn day(date: NaiveDate) -> Day {
+        Day {
+            heating_consumption: 48.0,
+            general_consumption: 12.0,
+            average_temperature: 18.1,
+            date,
+        }
+    }
+
+    #[test]
+    fn day_state_resets_at_the_local_start_of_the_day() {
+        let state = day_state(
+            &day(NaiveDate::from_ymd_opt(2026, 10, 18).unwrap()),
+            &config(),
+            Berlin,
+        );
+        assert_eq!(state.last_reset, "2026-10-18T00:00:00+02:00");
+        // the base fee of 31€ is split over the days of October
+        assert!((state.heating_cost - (48.0 * 0.25 + 1.0)).abs() < 1e-9);
+        assert!((state.general_cost - 12.0 * 0.3).abs() < 1e-9);
+
+        // the clocks are set back during the 25th
+        for (day_of_month, offset) in [(25, "+02:00"), (26, "+01:00")] {
+            let date = NaiveDate::from_ymd_opt(2026, 10, day_of_month).unwrap();
+            let state = day_state(&day(date), &config(), Berlin);
+            assert_eq!(state.last_reset, format!("{}T00:00:00{}", date, offset));
+        }
+    }
+
+    #[test]
+    fn month_state_compares_the_costs_with_the_budgets() {
+        let month = Aggregate {
+            period: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            days: 18,
+            heating_sum: 200.0,
+            heating_avg: 0.0,
+            heating_min: 0.0,
+            heating_max: 0.0,
+            general_sum: 100.0,
+            general_avg: 0.0,
+            general_min: 0.0,
+            general_max: 0.0,
+            average_temperature: 12.0,
+            heating_cost: 50.0,
+            general_cost: 30.0,
+        };
+        let state = month_state(Some(&month), &config());
+        assert_eq!((state.heating_cost, state.general_cost), (50.0, 30.0));
+        assert_eq!(state.heating_budget_used, 25.0);
+        // without a budget, nothing of it is used
+        assert_eq!(state.general_budget_used, 0.0);
+
+        let empty = month_state(None, &config());
+        assert_eq!((empty.heating_cost, empty.heating_budget_used), (0.0, 0.0));
+        assert_eq!(empty.heating_budget, 200.0);
+    }
+
+    #[test]
+    fn discovery_covers_every_value_of_the_states() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let day = serde_json::to_value(day_state(&day(date), &config(), Berlin)).unwrap();
+        let month = serde_json::to_value(month_state(None, &config())).unwrap();
+
+        let sensors = discovery("powerfox", "homeassistant");
+        assert_eq!(sensors.len(), 16);
+        assert_eq!(
+            sensors[0].0,
+            "homeassistant/sensor/powerfox/yesterday_heating_consumption/config"
+        );
+        let mut unique_ids: Vec<&str> = sensors
+            .iter()
+            .map(|(_, config)| config["unique_id"].as_str().unwrap())
+            .collect();
+        unique_ids.sort();
+        unique_ids.dedup();
+        assert_eq!(unique_ids.len(), sensors.len());
+
+        for (topic, config) in &sensors {
+            let state = match config["state_topic"].as_str().unwrap() {
+                "powerfox/yesterday" | "powerfox/today" => &day,
+                "powerfox/month" => &month,
+                other => panic!("unknown state-topic {}", other),
+            };
+            let object_id = topic.split('/').nth(3).unwrap();
+            assert_eq!(config["unique_id"], format!("powerfox_{}", object_id));
+            for template in ["value_template", "last_reset_value_template"] {
+                let Some(template) = config[template].as_str() else {
+                    continue;
+                };
+                let field = template
+                    .trim_start_matches("{{ value_json.")
+                    .trim_end_matches(" }}");
+                assert!(
+                    state.get(field).is_some(),
+                    "{} isn't in the state of {}",
+                    field,
+                    topic
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn energy_sensors_reset_at_the_start_of_the_day() {
+        let config = sensor_config(
+            "powerfox",
+            "yesterday",
+            "heating_consumption",
+            "kWh",
+            "energy",
+        );
+        assert_eq!(config["state_class"], "total");
+        assert_eq!(
+            config["last_reset_value_template"],
+            "{{ value_json.last_reset }}"
+        );
+    }
+
+    #[test]
+    fn other_sensors_have_no_state_class() {
+        for (value, unit, device_class) in [
+            ("average_temperature", "°C", "temperature"),
+            ("heating_cost", "EUR", "monetary"),
+        ] {
+            let config = sensor_config("powerfox", "today", value, unit, device_class);
+            assert_eq!(config["device_class"], device_class);
+            assert!(config.get("state_class").is_none());
+            assert_eq!(config["state_topic"], "powerfox/today");
+        }
+    }
+
+    /// Publishes to a real broker at `MQTT_TEST_HOST` and `MQTT_TEST_PORT` - `127.0.0.1:1883` by default -
+    /// and checks what a subscriber receives, see the README for running it.
+    #[tokio::test]
+    #[ignore]
+    async fn broker_receives_discovery_and_states() {
+        let host = env::var("MQTT_TEST_HOST").unwrap_or("127.0.0.1".to_string());
+        let port = env::var("MQTT_TEST_PORT").map_or(1883, |port| port.parse().unwrap());
+        let topic = "powerfox-test";
+        let discovery_prefix = "powerfox-test-discovery";
+
+        let (subscriber, mut events) = AsyncClient::new(
+            MqttOptions::new("powerfox-test-subscriber", &host, port),
+            QUEUE_SIZE,
+        );
+        for filter in [format!("{}/#", topic), format!("{}/#", discovery_prefix)] {
+            subscriber
+                .subscribe(filter, QoS::AtLeastOnce)
+                .await
+                .unwrap();
+        }
+        let mut acknowledged = 0;
+        while acknowledged < 2 {
+            if let Event::Incoming(Packet::SubAck(_)) = events.poll().await.unwrap() {
+                acknowledged += 1;
+            }
+        }
+
+        let (client, event_loop) = AsyncClient::new(
+            MqttOptions::new("powerfox-test-publisher", &host, port),
+            QUEUE_SIZE,
+        );
+        tokio::spawn(poll(event_loop));
+        let mqtt = Mqtt {
+            client,
+            topic: topic.to_string(),
+        };
+        let db: Db = Arc::new(Memory::new());
+        let day = day(util::yesterday());
+        mqtt.publish_discovery(discovery_prefix).unwrap();
+        mqtt.publish_states(&db, "yesterday", &day).await.unwrap();
+
+        let sensors = discovery(topic, discovery_prefix);
+        let mut received: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        let receiving = async {
+            while received.len() < sensors.len() + 2 {
+                match events.poll().await.unwrap() {
+                    // retained messages of earlier runs are delivered on subscribing, with the retain-flag
+                    Event::Incoming(Packet::Publish(publish)) if !publish.retain => {
+                        received.insert(publish.topic, publish.payload.to_vec());
+                    }
+                    _ => {}
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), receiving)
+            .await
+            .expect("the broker didn't forward every message");
+
+        // compared as sent, as parsing the JSON again could round the numbers differently
+        for (topic, config) in sensors {
+            assert_eq!(received[&topic], serde_json::to_vec(&config).unwrap());
+        }
+        let config = db.get_config().await.unwrap();
+        assert_eq!(
+            received[&state_topic(topic, "yesterday")],
+            serde_json::to_vec(&day_state(&day, &config, util::timezone())).unwrap()
+        );
+        let month: Value = serde_json::from_slice(&received[&state_topic(topic, "month")]).unwrap();
+        assert_eq!(month["heating_budget"], config.monthly_budget_heating);
+
+        // empty retained messages remove them from the broker
+        for topic in received.keys() {
+            mqtt.send(topic.clone(), Vec::new()).unwrap();
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
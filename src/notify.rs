@@ -0,0 +1,286 @@
+use crate::discord::say;
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use log::{error, info};
+use poise::serenity_prelude::ChannelId;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::json;
+use std::{collections::HashMap, env, sync::Arc};
+
+/// The notifiers used for a kind of message if its variable, e.g. `NOTIFY_DAILY`, isn't set.
+pub const DEFAULT_NOTIFIERS: &str = "discord";
+
+/// The kinds of messages, each of which is sent to its own notifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// The daily summary of yesterday and the current month.
+    Daily,
+    /// Something needing attention, like an exceeded budget.
+    Alert,
+    /// The daily job failed.
+    Error,
+}
+
+impl Kind {
+    fn title(&self) -> &'static str {
+        match self {
+            Kind::Daily => "Powerfox: daily summary",
+            Kind::Alert => "Powerfox: alert",
+            Kind::Error => "Powerfox: error",
+        }
+    }
+}
+
+/// Sends a message somewhere.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, kind: Kind, message: &str) -> Result<()>;
+}
+
+/// The notifiers per kind of message.
+#[derive(Clone)]
+pub struct Notifiers {
+    daily: Vec<Arc<dyn Notifier>>,
+    alert: Vec<Arc<dyn Notifier>>,
+    error: Vec<Arc<dyn Notifier>>,
+}
+
+impl Notifiers {
+    /// Reads the comma-separated notifiers per kind from `NOTIFY_DAILY`, `NOTIFY_ALERTS` and `NOTIFY_ERRORS`,
+    /// each defaulting to `discord`. Only the notifiers in use need to be configured.
+    pub fn from_env() -> Result<Self> {
+        let mut created: HashMap<String, Arc<dyn Notifier>> = HashMap::new();
+        let mut notifiers_for = |var: &str| -> Result<Vec<Arc<dyn Notifier>>> {
+            let names = env::var(var).unwrap_or(DEFAULT_NOTIFIERS.to_string());
+            let mut notifiers = Vec::new();
+            for name in names
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+            {
+                let notifier = match created.get(name) {
+                    Some(notifier) => notifier.clone(),
+                    None => {
+                        let notifier = create(name).map_err(|err| {
+                            anyhow!("Could not set up {} for {}: {}", name, var, err)
+                        })?;
+                        created.insert(name.to_string(), notifier.clone());
+                        notifier
+                    }
+                };
+                notifiers.push(notifier);
+            }
+            Ok(notifiers)
+        };
+
+        let notifiers = Notifiers {
+            daily: notifiers_for("NOTIFY_DAILY")?,
+            alert: notifiers_for("NOTIFY_ALERTS")?,
+            error: notifiers_for("NOTIFY_ERRORS")?,
+        };
+        info!(
+            "Notifying via {}.",
+            created.keys().cloned().collect::<Vec<_>>().join(", ")
+        );
+        Ok(notifiers)
+    }
+
+    /// Uses the same notifier for every kind of message.
+    pub fn all(notifier: Arc<dyn Notifier>) -> Self {
+        Notifiers {
+            daily: vec![notifier.clone()],
+            alert: vec![notifier.clone()],
+            error: vec![notifier],
+        }
+    }
+
+    /// Sends the message to every notifier of its kind, even if some of them fail.
+    pub async fn send(&self, kind: Kind, message: &str) -> Result<()> {
+        let notifiers = match kind {
+            Kind::Daily => &self.daily,
+            Kind::Alert => &self.alert,
+            Kind::Error => &self.error,
+        };
+        let mut failures = Vec::new();
+        for notifier in notifiers {
+            if let Err(err) = notifier.send(kind, message).await {
+                error!("Could not send {:?}-message: {}", kind, err);
+                failures.push(err.to_string());
+            }
+        }
+        if !failures.is_empty() {
+            bail!("Could not send message: {}", failures.join("; "));
+        }
+        Ok(())
+    }
+}
+
+/// Like [env::var], but names the missing variable.
+fn var(name: &str) -> Result<String> {
+    env::var(name).map_err(|_| anyhow!("{} is not set.", name))
+}
+
+fn create(name: &str) -> Result<Arc<dyn Notifier>> {
+    Ok(match name {
+        "discord" => Arc::new(Discord::from_env()?),
+        "webhook" => Arc::new(Webhook::from_env()?),
+        "ntfy" => Arc::new(Ntfy::from_env()?),
+        "email" => Arc::new(Email::from_env()?),
+        _ => bail!("Unknown notifier, use discord, webhook, ntfy or email."),
+    })
+}
+
+/// Posts to the channel `DISCORD_CHANNEL_ID`.
+struct Discord {
+    token: String,
+    channel_id: ChannelId,
+}
+
+impl Discord {
+    fn from_env() -> Result<Self> {
+        Ok(Discord {
+            token: var("DISCORD_TOKEN")?,
+            channel_id: ChannelId::new(var("DISCORD_CHANNEL_ID")?.parse()?),
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for Discord {
+    async fn send(&self, _kind: Kind, message: &str) -> Result<()> {
+        say(&self.token, self.channel_id, message).await
+    }
+}
+
+/// POSTs `{"kind", "title", "message"}` as JSON to `WEBHOOK_URL`.
+struct Webhook {
+    client: Client,
+    url: String,
+}
+
+impl Webhook {
+    fn from_env() -> Result<Self> {
+        Ok(Webhook {
+            client: Client::new(),
+            url: var("WEBHOOK_URL")?,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for Webhook {
+    async fn send(&self, kind: Kind, message: &str) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(&json!({
+                "kind": kind,
+                "title": kind.title(),
+                "message": message,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Pushes to the ntfy-topic at `NTFY_URL`, e.g. `https://ntfy.sh/<topic>`, using `NTFY_TOKEN` if it's set.
+struct Ntfy {
+    client: Client,
+    url: String,
+    token: Option<String>,
+}
+
+impl Ntfy {
+    fn from_env() -> Result<Self> {
+        Ok(Ntfy {
+            client: Client::new(),
+            url: var("NTFY_URL")?,
+            token: env::var("NTFY_TOKEN").ok(),
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for Ntfy {
+    async fn send(&self, kind: Kind, message: &str) -> Result<()> {
+        let priority = match kind {
+            Kind::Daily => "default",
+            Kind::Alert | Kind::Error => "high",
+        };
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Title", kind.title())
+            .header("Priority", priority)
+            // the messages are formatted for Discord
+            .header("Markdown", "yes")
+            .body(message.to_string());
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Sends mails from `SMTP_FROM` to the comma-separated `SMTP_TO` via `SMTP_HOST`.
+struct Email {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl Email {
+    fn from_env() -> Result<Self> {
+        let host = var("SMTP_HOST")?;
+        // `none` is only meant for local testing, as the password is sent in plain text
+        let mut builder = match env::var("SMTP_SECURITY").as_deref() {
+            Ok("tls") => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            Ok("starttls") | Err(_) => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?,
+            Ok("none") => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            Ok(security) => bail!(
+                "Unknown SMTP_SECURITY '{}', use tls, starttls or none.",
+                security
+            ),
+        };
+        if let Ok(port) = env::var("SMTP_PORT") {
+            builder = builder.port(port.parse()?);
+        }
+        if let Ok(username) = env::var("SMTP_USERNAME") {
+            builder = builder.credentials(Credentials::new(username, var("SMTP_PASSWORD")?));
+        }
+
+        let to = var("SMTP_TO")?
+            .split(',')
+            .map(|to| to.trim().parse())
+            .collect::<Result<Vec<Mailbox>, _>>()?;
+        Ok(Email {
+            transport: builder.build(),
+            from: var("SMTP_FROM")?.parse()?,
+            to,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for Email {
+    async fn send(&self, kind: Kind, message: &str) -> Result<()> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(kind.title());
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+        self.transport
+            .send(builder.body(message.to_string())?)
+            .await?;
+        Ok(())
+    }
+}
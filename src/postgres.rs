@@ -0,0 +1,241 @@
+use crate::db::{Aggregate, Config, ConfigHistory, CreateDay, Day, Days, Device, Granularity, Hour, JobRun, Peak, Storage, Trigger};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use log::info;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    types::Uuid,
+    PgConnection, PgPool,
+};
+use std::env;
+
+/// [Storage] backed by PostgreSQL.
+#[derive(Clone)]
+pub struct Postgres {
+    pool: PgPool,
+}
+
+
+impl Postgres {
+    /// Connects to the database, which is migrated unless it's opened read-only.
+    pub async fn new(read_only: bool) -> Result<Self> {
+        let username = env::var("DATABASE_USER")?;
+        let password = env::var("DATABASE_PASSWORD")?;
+        let host = env::var("DATABASE_HOST")?;
+        let port: u16 = env::var("DATABASE_PORT")?.parse()?;
+        let database = env::var("DATABASE_TABLE")?;
+
+        let mut options = PgConnectOptions::new()
+            .host(&host)
+            .port(port)
+            .username(&username)
+            .password(&password)
+            .database(&database);
+        if read_only {
+            options = options.options([("default_transaction_read_only", "on")]);
+        }
+
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await?;
+
+        // migrations are embedded at compile-time and only applied once
+        if !read_only {
+            sqlx::migrate!("migrations/postgres").run(&pool).await?;
+        }
+
+        info!("Set up database-client.");
+        Ok(Postgres {pool})
+    }
+}
+
+/// Saves the day within the transaction, replacing an existing day with the same date.
+async fn insert_day(connection: &mut PgConnection, day: CreateDay) -> Result<Day> {
+    let day = sqlx::query_as!(Day,
+        "INSERT INTO days (id, heating_consumption, general_consumption, average_temperature, date) VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (date) DO UPDATE SET (heating_consumption, general_consumption, average_temperature) = (EXCLUDED.heating_consumption, EXCLUDED.general_consumption, EXCLUDED.average_temperature)
+        RETURNING heating_consumption, general_consumption, average_temperature, date",
+        Uuid::new_v4(), day.heating_consumption, day.general_consumption, day.average_temperature, day.date)
+        .fetch_one(connection)
+        .await?;
+    Ok(day)
+}
+
+#[async_trait]
+impl Storage for Postgres {
+    async fn save_days(&self, days: Vec<CreateDay>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for day in days {
+            insert_day(&mut tx, day).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn save_import(&self, day: CreateDay, hours: Vec<Hour>, peak: Peak) -> Result<Day> {
+        let mut tx = self.pool.begin().await?;
+        let day = insert_day(&mut tx, day).await?;
+        for hour in hours {
+            sqlx::query!(
+                "INSERT INTO hours (timestamp, date, heating_consumption, general_consumption) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (timestamp) DO UPDATE SET (date, heating_consumption, general_consumption) = (EXCLUDED.date, EXCLUDED.heating_consumption, EXCLUDED.general_consumption)",
+                hour.timestamp, hour.date, hour.heating_consumption, hour.general_consumption)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query!(
+            "INSERT INTO peaks (date, heating_max, general_max) VALUES ($1, $2, $3)
+            ON CONFLICT (date) DO UPDATE SET (heating_max, general_max) = (EXCLUDED.heating_max, EXCLUDED.general_max)",
+            peak.date, peak.heating_max, peak.general_max)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(day)
+    }
+
+    async fn get_day(&self, date: NaiveDate) -> Result<Option<Day>> {
+        let day = sqlx::query_as!(Day, "SELECT heating_consumption, general_consumption, average_temperature, date FROM days WHERE date = $1", date)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(day)
+    }
+
+    async fn get_latest_day(&self) -> Result<Option<Day>> {
+        let day = sqlx::query_as!(Day, "SELECT heating_consumption, general_consumption, average_temperature, date FROM days ORDER BY date DESC LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(day)
+    }
+
+    async fn get_days_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Days> {
+        let days = sqlx::query_as!(Day, "SELECT heating_consumption, general_consumption, average_temperature, date FROM days WHERE date BETWEEN $1 AND $2 ORDER BY date", from, to)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(Days(days))
+    }
+
+    async fn get_hours_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Hour>> {
+        let hours = sqlx::query_as!(Hour, "SELECT timestamp, date, heating_consumption, general_consumption FROM hours WHERE date BETWEEN $1 AND $2 ORDER BY timestamp", from, to)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(hours)
+    }
+
+    async fn get_peaks_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Peak>> {
+        let peaks = sqlx::query_as!(Peak, "SELECT date, heating_max, general_max FROM peaks WHERE date BETWEEN $1 AND $2 ORDER BY date", from, to)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(peaks)
+    }
+
+    async fn aggregate(&self, from: NaiveDate, to: NaiveDate, granularity: Granularity) -> Result<Vec<Aggregate>> {
+        // joins the config valid on each day, falling back to the first config like ConfigHistory::at
+        let aggregates = sqlx::query_as!(Aggregate,
+            r#"SELECT date_trunc($3, d.date::timestamp)::date AS "period!",
+                count(*) AS "days!",
+                sum(d.heating_consumption) AS "heating_sum!",
+                avg(d.heating_consumption) AS "heating_avg!",
+                min(d.heating_consumption) AS "heating_min!",
+                max(d.heating_consumption) AS "heating_max!",
+                sum(d.general_consumption) AS "general_sum!",
+                avg(d.general_consumption) AS "general_avg!",
+                min(d.general_consumption) AS "general_min!",
+                max(d.general_consumption) AS "general_max!",
+                avg(d.average_temperature) AS "average_temperature!",
+                sum(d.heating_consumption * c.cost_heating + c.base_fee_heating / extract(day FROM date_trunc('month', d.date::timestamp) + interval '1 month - 1 day')::double precision) AS "heating_cost!",
+                sum(d.general_consumption * c.cost_general + c.base_fee_general / extract(day FROM date_trunc('month', d.date::timestamp) + interval '1 month - 1 day')::double precision) AS "general_cost!"
+            FROM days d
+            JOIN config c ON c.valid_from = COALESCE(
+                (SELECT max(valid_from) FROM config WHERE valid_from <= d.date),
+                (SELECT min(valid_from) FROM config))
+            WHERE d.date BETWEEN $1 AND $2
+            GROUP BY 1
+            ORDER BY 1"#,
+            from, to, granularity.as_str())
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(aggregates)
+    }
+
+    async fn get_config_history(&self) -> Result<ConfigHistory> {
+        let configs = sqlx::query_as!(Config, "SELECT * FROM config ORDER BY valid_from")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(ConfigHistory(configs))
+    }
+
+    async fn save_config(&self, config: Config) -> Result<Config> {
+        let config = sqlx::query_as!(Config,
+            "INSERT INTO config (valid_from, cost_heating, cost_general, monthly_budget_heating, monthly_budget_general, base_fee_heating, base_fee_general) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (valid_from) DO UPDATE SET (cost_heating, cost_general, monthly_budget_heating, monthly_budget_general, base_fee_heating, base_fee_general) = (EXCLUDED.cost_heating, EXCLUDED.cost_general, EXCLUDED.monthly_budget_heating, EXCLUDED.monthly_budget_general, EXCLUDED.base_fee_heating, EXCLUDED.base_fee_general)
+            RETURNING valid_from, cost_heating, cost_general, monthly_budget_heating, monthly_budget_general, base_fee_heating, base_fee_general",
+            config.valid_from, config.cost_heating, config.cost_general, config.monthly_budget_heating, config.monthly_budget_general, config.base_fee_heating, config.base_fee_general)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(config)
+    }
+
+    async fn get_schedule(&self, name: &str) -> Result<Option<String>> {
+        let cron = sqlx::query_scalar!("SELECT cron FROM schedules WHERE name = $1", name)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(cron)
+    }
+
+    async fn save_schedule(&self, name: &str, cron: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO schedules (name, cron) VALUES ($1, $2) ON CONFLICT (name) DO UPDATE SET cron = EXCLUDED.cron",
+            name, cron)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_devices(&self) -> Result<Vec<Device>> {
+        let devices = sqlx::query_as!(Device, "SELECT * FROM devices ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(devices)
+    }
+
+    async fn save_devices(&self, devices: Vec<Device>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for device in devices {
+            sqlx::query!(
+                "INSERT INTO devices (device_id, name, main_device) VALUES ($1, $2, $3)
+                ON CONFLICT (device_id) DO UPDATE SET (name, main_device) = (EXCLUDED.name, EXCLUDED.main_device)",
+                device.device_id, device.name, device.main_device)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn start_job_run(&self, job: &str, trigger: Trigger) -> Result<i64> {
+        let id = sqlx::query_scalar!(
+            "INSERT INTO job_runs (job, triggered_by, started_at, status) VALUES ($1, $2, $3, 'running') RETURNING id",
+            job, trigger.as_str(), Utc::now())
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(id)
+    }
+
+    async fn finish_job_run(&self, id: i64, error: Option<String>, days: Vec<NaiveDate>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE job_runs SET (finished_at, status, error, days) = ($2, $3, $4, $5) WHERE id = $1",
+            id, Utc::now(), JobRun::status_of(&error), error, JobRun::join_days(&days))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_job_runs(&self, limit: i64) -> Result<Vec<JobRun>> {
+        let runs = sqlx::query_as!(JobRun, "SELECT * FROM job_runs ORDER BY started_at DESC, id DESC LIMIT $1", limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(runs)
+    }
+}
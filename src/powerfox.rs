@@ -1,10 +1,11 @@
 use std::env;
 
+use crate::metrics;
 use anyhow::{anyhow, Result};
 use chrono::serde::ts_seconds::deserialize as from_ts;
-use chrono::{Datelike, Duration, Local, Utc};
+use chrono::{Datelike, NaiveDate, Utc};
 use reqwest::{Client, StatusCode};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 pub struct Powerfox {
@@ -30,72 +31,40 @@ impl Powerfox {
 
     /// Get all devices linked to the specified account.
     pub async fn get_devices(&self) -> Result<Vec<Device>> {
-        let response = self
-            .client
-            .get(format!("{}/api/2.0/my/all/devices", &self.base_url))
-            .basic_auth(&self.username, Some(&self.password))
-            .send()
-            .await?;
-        if response.status() != StatusCode::OK {
-            Err(anyhow!(
-                "Status-Code of response was not OK: {}",
-                response.text().await?
-            ))
-        } else {
-            Ok(response.json().await?)
-        }
+        self.get("/api/2.0/my/all/devices").await
     }
 
-    /// Get the values of the specified device for the last 24 hours.
-    pub async fn get_report_for_yesterday(&self, device_id: &String) -> Result<Report> {
-        let yesterday = Local::now().date_naive() - Duration::days(1);
-        let response = self
-            .client
-            .get(format!(
-                "{}/api/2.0/my/{}/report?year={}&month={}&day={}",
-                &self.base_url,
-                device_id,
-                yesterday.year(),
-                yesterday.month(),
-                yesterday.day()
-            ))
-            .basic_auth(&self.username, Some(&self.password))
-            .send()
-            .await?;
-        if response.status() != StatusCode::OK {
-            Err(anyhow!(
-                "Status-Code of response was not OK: {}",
-                response.text().await?
-            ))
-        } else {
-            Ok(response.json().await?)
-        }
+    /// Get the values of the specified device for the given date.
+    pub async fn get_report(&self, device_id: &String, date: NaiveDate) -> Result<Report> {
+        self.get(&format!(
+            "/api/2.0/my/{}/report?year={}&month={}&day={}",
+            device_id,
+            date.year(),
+            date.month(),
+            date.day()
+        ))
+        .await
     }
 
-    /// Get the values of the specified device for today, starting from 00:00.
-    pub async fn get_report_for_today(&self, device_id: &String) -> Result<Report> {
-        let today = Local::now().date_naive();
-        let response = self
-            .client
-            .get(format!(
-                "{}/api/2.0/my/{}/report?year={}&month={}&day={}",
-                &self.base_url,
-                device_id,
-                today.year(),
-                today.month(),
-                today.day()
-            ))
-            .basic_auth(&self.username, Some(&self.password))
-            .send()
-            .await?;
-        if response.status() != StatusCode::OK {
-            Err(anyhow!(
-                "Status-Code of response was not OK: {}",
-                response.text().await?
-            ))
-        } else {
-            Ok(response.json().await?)
-        }
+    /// Sends a request to the given path, which is counted and timed in the [metrics].
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        metrics::observe_request(metrics::POWERFOX, async {
+            let response = self
+                .client
+                .get(format!("{}{}", &self.base_url, path))
+                .basic_auth(&self.username, Some(&self.password))
+                .send()
+                .await?;
+            if response.status() != StatusCode::OK {
+                Err(anyhow!(
+                    "Status-Code of response was not OK: {}",
+                    response.text().await?
+                ))
+            } else {
+                Ok(response.json().await?)
+            }
+        })
+        .await
     }
 }
 
@@ -0,0 +1,213 @@
+use crate::db::Hour;
+use chrono::{Datelike, NaiveDate, Timelike, Weekday};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Shades from no consumption to the highest one, see [shade].
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+/// The average consumption of both meters in kWh, e.g. at an hour of the day.
+#[derive(Default, Clone, Copy)]
+pub struct Average {
+    pub heating: f64,
+    pub general: f64,
+}
+
+impl Average {
+    pub fn total(&self) -> f64 {
+        self.heating + self.general
+    }
+
+    fn of(values: &[(f64, f64)]) -> Self {
+        if values.is_empty() {
+            return Average::default();
+        }
+        let count = values.len() as f64;
+        Average {
+            heating: values.iter().fold(0.0, |sum, (heating, _)| sum + heating) / count,
+            general: values.iter().fold(0.0, |sum, (_, general)| sum + general) / count,
+        }
+    }
+}
+
+/// The hour of a day with the highest consumption of both meters.
+pub struct PeakHour {
+    pub date: NaiveDate,
+    /// The hour of the day in the local time.
+    pub hour: u32,
+    pub consumption: f64,
+}
+
+/// Averages the consumption per hour of the day in the timezone, starting at midnight.
+pub fn by_hour(hours: &[Hour], timezone: Tz) -> Vec<Average> {
+    let mut values = vec![Vec::new(); 24];
+    for hour in hours {
+        let local = hour.timestamp.with_timezone(&timezone).hour() as usize;
+        values[local].push((hour.heating_consumption, hour.general_consumption));
+    }
+    values.iter().map(|values| Average::of(values)).collect()
+}
+
+/// Averages the consumption per hour of the day for each weekday in the timezone, starting on Monday at midnight.
+pub fn by_weekday(hours: &[Hour], timezone: Tz) -> Vec<(Weekday, Vec<Average>)> {
+    let mut values = vec![vec![Vec::new(); 24]; 7];
+    for hour in hours {
+        let local = hour.timestamp.with_timezone(&timezone);
+        values[local.weekday().num_days_from_monday() as usize][local.hour() as usize]
+            .push((hour.heating_consumption, hour.general_consumption));
+    }
+    WEEKDAYS
+        .into_iter()
+        .zip(values)
+        .map(|(weekday, values)| {
+            (
+                weekday,
+                values.iter().map(|values| Average::of(values)).collect(),
+            )
+        })
+        .collect()
+}
+
+/// Finds the hour with the highest consumption of each day, the earliest one if several are equally high.
+pub fn peak_hours(hours: &[Hour], timezone: Tz) -> Vec<PeakHour> {
+    let mut peaks: BTreeMap<NaiveDate, PeakHour> = BTreeMap::new();
+    let mut hours: Vec<&Hour> = hours.iter().collect();
+    hours.sort_by_key(|hour| hour.timestamp);
+    for hour in hours {
+        let consumption = hour.heating_consumption + hour.general_consumption;
+        let peak = peaks.entry(hour.date).or_insert(PeakHour {
+            date: hour.date,
+            hour: 0,
+            consumption: f64::MIN,
+        });
+        if consumption > peak.consumption {
+            peak.hour = hour.timestamp.with_timezone(&timezone).hour();
+            peak.consumption = consumption;
+        }
+    }
+    peaks.into_values().collect()
+}
+
+/// Draws a bar of up to `width` characters for the value relative to the maximum.
+pub fn bar(value: f64, max: f64, width: usize) -> String {
+    let length = if max > 0.0 {
+        (value / max * width as f64).round() as usize
+    } else {
+        0
+    };
+    "█".repeat(length.min(width))
+}
+
+/// Shades the value relative to the maximum, from blank for nothing to a full block for the maximum.
+pub fn shade(value: f64, max: f64) -> char {
+    let index = if max > 0.0 {
+        (value / max * (SHADES.len() - 1) as f64).round() as usize
+    } else {
+        0
+    };
+    SHADES[index.min(SHADES.len() - 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use chrono_tz::Europe::Berlin;
+
+    /// An hour starting at the UTC-time, with the date it has in Berlin.
+    fn hour(timestamp: DateTime<Utc>, heating_consumption: f64, general_consumption: f64) -> Hour {
+        Hour {
+            timestamp,
+            date: timestamp.with_timezone(&Berlin).date_naive(),
+            heating_consumption,
+            general_consumption,
+        }
+    }
+
+    /// Sunday, the 11th of January 2026 at 23:00 UTC, which is already Monday in Berlin.
+    fn sunday_night() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 11, 23, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn hours_are_in_the_local_time() {
+        let hours = [hour(sunday_night(), 1.0, 2.0)];
+        let by_hour = by_hour(&hours, Berlin);
+        assert_eq!(by_hour.len(), 24);
+        assert_eq!((by_hour[0].heating, by_hour[0].general), (1.0, 2.0));
+        assert_eq!(by_hour[23].total(), 0.0);
+        assert_eq!(super::by_hour(&hours, Tz::UTC)[23].total(), 3.0);
+    }
+
+    #[test]
+    fn weekdays_are_in_the_local_time() {
+        let hours = [
+            hour(sunday_night(), 1.0, 2.0),
+            hour(sunday_night() - Duration::hours(1), 4.0, 0.0),
+            // the next Monday at midnight
+            hour(sunday_night() + Duration::days(7), 3.0, 0.0),
+        ];
+        let by_weekday = by_weekday(&hours, Berlin);
+        assert_eq!(
+            by_weekday
+                .iter()
+                .map(|(weekday, _)| *weekday)
+                .collect::<Vec<_>>(),
+            WEEKDAYS
+        );
+        let (_, monday) = &by_weekday[0];
+        assert_eq!(monday.len(), 24);
+        assert_eq!((monday[0].heating, monday[0].general), (2.0, 1.0));
+        let (_, sunday) = &by_weekday[6];
+        assert_eq!(sunday[23].heating, 4.0);
+        assert_eq!(sunday[22].total(), 0.0);
+
+        let in_utc = super::by_weekday(&hours, Tz::UTC);
+        assert_eq!(in_utc[6].1[23].heating, 2.0);
+        assert_eq!(in_utc[0].1[0].total(), 0.0);
+    }
+
+    #[test]
+    fn peak_hour_is_the_earliest_of_equal_ones() {
+        let morning = Utc.with_ymd_and_hms(2026, 1, 12, 6, 0, 0).unwrap();
+        let hours = [
+            hour(morning + Duration::hours(3), 1.0, 1.0),
+            hour(morning + Duration::hours(1), 0.5, 1.5),
+            hour(morning, 1.0, 0.5),
+            // still Monday in Berlin
+            hour(sunday_night(), 0.1, 0.1),
+        ];
+        let peaks = peak_hours(&hours, Berlin);
+        assert_eq!(peaks.len(), 1);
+        assert_eq!(peaks[0].date, NaiveDate::from_ymd_opt(2026, 1, 12).unwrap());
+        assert_eq!(peaks[0].hour, 8);
+        assert_eq!(peaks[0].consumption, 2.0);
+    }
+
+    #[test]
+    fn peak_hour_is_in_the_local_time() {
+        let hours = [hour(sunday_night(), 1.0, 1.0)];
+        assert_eq!(peak_hours(&hours, Berlin)[0].hour, 0);
+        assert_eq!(peak_hours(&hours, Tz::UTC)[0].hour, 23);
+    }
+
+    #[test]
+    fn shades_and_bars_are_relative_to_the_maximum() {
+        assert_eq!(shade(0.0, 2.0), ' ');
+        assert_eq!(shade(1.0, 2.0), '▒');
+        assert_eq!(shade(2.0, 2.0), '█');
+        assert_eq!(shade(1.0, 0.0), ' ');
+        assert_eq!(bar(1.0, 2.0, 4), "██");
+        assert_eq!(bar(3.0, 2.0, 4), "████");
+    }
+}
@@ -0,0 +1,308 @@
+use crate::{
+    anomaly,
+    db::{Db, Trigger},
+    fetch, metrics,
+    notify::{Kind, Notifiers},
+    util,
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use log::{error, info};
+use std::{env, future::Future, str::FromStr, sync::Arc};
+use tokio::sync::Mutex;
+use tokio_cron_scheduler::{Job, JobScheduler};
+use uuid::Uuid;
+
+/// The name under which the schedule of the daily job is stored.
+pub const DAILY: &str = "daily";
+
+/// Used if neither the database nor `DAILY_SCHEDULE` contain a schedule for the daily job.
+const DEFAULT_DAILY_SCHEDULE: &str = "0 0 12 * * * *";
+
+/// Runs the scheduled jobs in the configured timezone, see [util::timezone].
+///
+/// NOTE the scheduler only supports fixed UTC-offsets, so the daily job is a one-shot job at its next tick
+/// in the timezone, which is armed again after every run - this way, DST-switches don't shift it.
+#[derive(Clone)]
+pub struct Scheduler {
+    sched: JobScheduler,
+    db: Db,
+    notifiers: Notifiers,
+    daily: Arc<Mutex<Daily>>,
+}
+
+/// The schedule of the daily job and its next run.
+struct Daily {
+    cron: String,
+    job: Uuid,
+    /// Missing if arming the job failed.
+    tick: Option<DateTime<Utc>>,
+}
+
+impl Scheduler {
+    /// Creates the scheduler, adds the daily job and starts it.
+    pub async fn start(db: Db, notifiers: Notifiers) -> Result<Self> {
+        let sched = JobScheduler::new().await?;
+        let cron = Self::daily_schedule(&db).await?;
+        let scheduler = Scheduler {
+            sched,
+            db,
+            notifiers,
+            daily: Arc::new(Mutex::new(Daily {
+                cron: cron.clone(),
+                job: Uuid::nil(),
+                tick: None,
+            })),
+        };
+        scheduler
+            .arm_daily(&mut *scheduler.daily.lock().await, Utc::now())
+            .await?;
+        scheduler.sched.start().await?;
+
+        info!(
+            "Scheduled daily job with '{}' in {}.",
+            cron,
+            util::timezone()
+        );
+        Ok(scheduler)
+    }
+
+    /// The schedule of the daily job. Uses the schedule stored in the database,
+    /// then `DAILY_SCHEDULE` and falls back to every day at noon.
+    pub async fn daily_schedule(db: &Db) -> Result<String> {
+        if let Some(cron) = db.get_schedule(DAILY).await? {
+            return Ok(cron);
+        }
+        Ok(env::var("DAILY_SCHEDULE").unwrap_or(DEFAULT_DAILY_SCHEDULE.to_string()))
+    }
+
+    /// Replaces the daily job with one running on the given schedule and stores that schedule.
+    pub async fn reschedule_daily(&self, cron: &str) -> Result<()> {
+        // validate first so invalid schedules don't remove the existing job
+        next_tick(cron, util::now())?;
+
+        let mut daily = self.daily.lock().await;
+        self.sched.remove(&daily.job).await?;
+        daily.cron = cron.to_string();
+        self.arm_daily(&mut daily, Utc::now()).await?;
+        self.db.save_schedule(DAILY, cron).await?;
+
+        info!("Rescheduled daily job with '{}'.", cron);
+        Ok(())
+    }
+
+    /// Stores the schedule of the daily job without rescheduling it, so it's used once the app is (re-)started.
+    pub async fn save_daily_schedule(db: &Db, cron: &str) -> Result<()> {
+        next_tick(cron, util::now())?;
+        db.save_schedule(DAILY, cron).await
+    }
+
+    /// The next time the daily job runs, in the configured timezone.
+    pub async fn next_daily(&self) -> Result<Option<DateTime<Tz>>> {
+        let daily = self.daily.lock().await;
+        Ok(daily.tick.map(|tick| tick.with_timezone(&util::timezone())))
+    }
+
+    /// Runs the daily job now, independent of its schedule.
+    pub async fn run_daily(&self, trigger: Trigger) -> Result<()> {
+        powerfox_daily(&self.db, &self.notifiers, util::yesterday(), trigger).await
+    }
+
+    /// Adds a one-shot job at the first tick of the daily schedule after the given time.
+    async fn arm_daily(&self, daily: &mut Daily, after: DateTime<Utc>) -> Result<()> {
+        daily.tick = None;
+        let tick =
+            next_tick(&daily.cron, after.with_timezone(&util::timezone()))?.with_timezone(&Utc);
+        daily.job = self.sched.add(self.daily_job(tick)?).await?;
+        daily.tick = Some(tick);
+        Ok(())
+    }
+
+    /// Creates a one-shot job at the tick, which arms the following one after it ran.
+    fn daily_job(&self, tick: DateTime<Utc>) -> Result<Job> {
+        let delay = (tick - Utc::now()).to_std().unwrap_or_default();
+        let scheduler = self.clone();
+        let job = Job::new_one_shot_async(delay, move |id, _l| {
+            let scheduler = scheduler.clone();
+            Box::pin(async move {
+                // the scheduler may fire one-shot jobs slightly early, e.g. still on the previous day
+                if let Ok(early) = (tick - Utc::now()).to_std() {
+                    tokio::time::sleep(early).await;
+                }
+                // already reported, so the scheduler only needs to keep running
+                let _ = scheduler.run_daily(Trigger::Schedule).await;
+
+                let mut daily = scheduler.daily.lock().await;
+                // the job was replaced while it ran, which armed the next run already
+                if daily.job != id {
+                    return;
+                }
+                if let Err(err) = scheduler.arm_daily(&mut daily, Utc::now()).await {
+                    error!("Could not schedule the next daily job: {}", err);
+                }
+            })
+        })?;
+        Ok(job)
+    }
+}
+
+/// The first time after the given one matching the schedule, in the timezone of that time.
+pub fn next_tick(cron: &str, after: DateTime<Tz>) -> Result<DateTime<Tz>> {
+    let schedule =
+        Schedule::from_str(cron).map_err(|err| anyhow!("Invalid schedule '{}': {}", cron, err))?;
+    schedule
+        .after(&after)
+        .next()
+        .ok_or(anyhow!("The schedule '{}' has no upcoming time.", cron))
+}
+
+/// Imports the given date - usually yesterday - and sends its summary and the summary of its month.
+/// The run is saved with its outcome, see [Storage::start_job_run](crate::db::Storage::start_job_run).
+/// Fails only if the run couldn't be saved, after reporting that like a failed run.
+pub async fn powerfox_daily(
+    db: &Db,
+    notifiers: &Notifiers,
+    date: NaiveDate,
+    trigger: Trigger,
+) -> Result<()> {
+    let (result, saved) =
+        match record_run(db, DAILY, trigger, date, daily(db, notifiers, date)).await {
+            Ok(result) => (result, Ok(())),
+            Err(err) => (Err(anyhow!("Could not save the run: {}", err)), Err(err)),
+        };
+    metrics::record_job(DAILY, result.is_ok());
+
+    if let Err(err) = result {
+        let error = format!("Encountered an error: {}", err);
+        error!("{}", error);
+        // this is the last place to report the error, so failing to send it is only logged
+        if let Err(err) = notifiers.send(Kind::Error, &error).await {
+            error!("Could not send the error: {}", err);
+        }
+    }
+    saved
+}
+
+/// Runs a job that may import the given date, saving the run with its outcome and the date if the run wrote it.
+/// The job's own result is returned as is, so the caller decides how to report its errors.
+pub async fn record_run<T>(
+    db: &Db,
+    job: &str,
+    trigger: Trigger,
+    date: NaiveDate,
+    run: impl Future<Output = Result<T>>,
+) -> Result<Result<T>> {
+    let id = db.start_job_run(job, trigger).await?;
+    // the day is only written if it isn't stored yet
+    let missing = matches!(db.get_day(date).await, Ok(None));
+    let result = run.await;
+
+    let written = missing && matches!(db.get_day(date).await, Ok(Some(_)));
+    let days = if written { vec![date] } else { Vec::new() };
+    let error = result.as_ref().err().map(|err| err.to_string());
+    db.finish_job_run(id, error, days).await?;
+    Ok(result)
+}
+
+async fn daily(db: &Db, notifiers: &Notifiers, date: NaiveDate) -> Result<()> {
+    info!("Getting data for {}.", date);
+    let day = fetch::create_day(db, date).await?;
+    info!("Got data for {}.", day.date);
+
+    let configs = db.get_config_history().await?;
+    let days = db
+        .get_days_between(util::first_of_month(date), date)
+        .await?;
+    let mut message = format!(
+        "{}\n{}",
+        day.summary(configs.at(day.date)?),
+        days.summary(&configs)?
+    );
+    let history = db
+        .get_days_between(date - Duration::days(anomaly::HISTORY_DAYS), date)
+        .await?;
+    for anomaly in anomaly::detect(&day, &history, configs.at(day.date)?)? {
+        message.push('\n');
+        message.push_str(&anomaly.summary());
+    }
+    notifiers.send(Kind::Daily, &message).await?;
+
+    let config = configs.current()?;
+    let budgets = [
+        (
+            "heating",
+            days.heating_cost(&configs)?,
+            config.monthly_budget_heating,
+        ),
+        (
+            "general consumption",
+            days.general_cost(&configs)?,
+            config.monthly_budget_general,
+        ),
+    ];
+    for (meter, cost, budget) in budgets {
+        if cost > budget {
+            let alert = format!(
+                "You've exceeded this month's budget for {}: {:.2} € of {:.2} €.",
+                meter, cost, budget
+            );
+            notifiers.send(Kind::Alert, &alert).await?;
+        }
+    }
+    info!("Done with daily data and summary.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use chrono_tz::Europe::Berlin;
+
+    /// The first ticks of the schedule after the given time, like the daily job arms them one after another.
+    fn ticks(cron: &str, after: DateTime<Tz>, count: usize) -> Vec<DateTime<Utc>> {
+        let mut ticks = Vec::new();
+        let mut after = after;
+        for _ in 0..count {
+            after = next_tick(cron, after).unwrap();
+            ticks.push(after.with_timezone(&Utc));
+        }
+        ticks
+    }
+
+    fn utc(month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, month, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn daily_ticks_stay_at_the_local_time() {
+        let after = Berlin.with_ymd_and_hms(2026, 1, 10, 8, 0, 0).unwrap();
+        // noon in winter is 11:00 in UTC - on every day, not only the first one
+        assert_eq!(
+            ticks(DEFAULT_DAILY_SCHEDULE, after, 3),
+            vec![utc(1, 10, 11), utc(1, 11, 11), utc(1, 12, 11)]
+        );
+    }
+
+    #[test]
+    fn daily_ticks_follow_dst_switches() {
+        let after = Berlin.with_ymd_and_hms(2026, 3, 28, 8, 0, 0).unwrap();
+        // the clocks are set forward in the night to March 29th
+        assert_eq!(
+            ticks(DEFAULT_DAILY_SCHEDULE, after, 2),
+            vec![utc(3, 28, 11), utc(3, 29, 10)]
+        );
+    }
+
+    #[test]
+    fn next_tick_is_after_the_given_time() {
+        let tick = Berlin.with_ymd_and_hms(2026, 1, 10, 12, 0, 0).unwrap();
+        assert_eq!(
+            next_tick(DEFAULT_DAILY_SCHEDULE, tick).unwrap(),
+            Berlin.with_ymd_and_hms(2026, 1, 11, 12, 0, 0).unwrap()
+        );
+        assert!(next_tick("not a schedule", tick).is_err());
+    }
+}
@@ -0,0 +1,537 @@
+use crate::{
+    cli::{Command, DevicesCommand},
+    notify::DEFAULT_NOTIFIERS,
+    DEFAULT_FRONTENDS,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono_tz::Tz;
+use cron::Schedule;
+use lettre::message::Mailbox;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeSet,
+    env,
+    fmt::Display,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// The config-file used if `CONFIG_FILE` isn't set - it is optional.
+const DEFAULT_FILE: &str = "powerfox.toml";
+
+/// The sections of the config-file, which are the prefixes of their variables.
+const SECTIONS: [&str; 11] = [
+    "powerfox", "weather", "database", "notify", "webhook", "ntfy", "smtp", "mqtt", "discord",
+    "matrix", "telegram",
+];
+
+/// The variables holding secrets, which can also be read from files, see [load_secrets].
+const SECRETS: [&str; 9] = [
+    "HTTP_TOKEN",
+    "POWERFOX_PASSWORD",
+    "DATABASE_PASSWORD",
+    "DISCORD_TOKEN",
+    "MATRIX_ACCESS_TOKEN",
+    "TELEGRAM_TOKEN",
+    "SMTP_PASSWORD",
+    "MQTT_PASSWORD",
+    "NTFY_TOKEN",
+];
+
+/// The config-file, in which every setting is named like its variable:
+/// `http_address` is `HTTP_ADDRESS` and `host` in `[database]` is `DATABASE_HOST`.
+/// Lists are joined with commas, so `frontends = ["discord", "telegram"]` is `FRONTENDS=discord,telegram`.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct Settings {
+    timezone: Option<String>,
+    daily_schedule: Option<String>,
+    http_address: Option<String>,
+    http_token: Option<String>,
+    health_max_age: Option<i64>,
+    anomaly_threshold: Option<f64>,
+    frontends: Option<Vec<String>>,
+    powerfox: Option<PowerfoxSettings>,
+    weather: Option<WeatherSettings>,
+    database: Option<DatabaseSettings>,
+    notify: Option<NotifySettings>,
+    webhook: Option<WebhookSettings>,
+    ntfy: Option<NtfySettings>,
+    smtp: Option<SmtpSettings>,
+    mqtt: Option<MqttSettings>,
+    discord: Option<DiscordSettings>,
+    matrix: Option<MatrixSettings>,
+    telegram: Option<TelegramSettings>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct PowerfoxSettings {
+    base_url: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    heating_device: Option<String>,
+    general_device: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct WeatherSettings {
+    base_url: Option<String>,
+    archive_url: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct DatabaseSettings {
+    backend: Option<String>,
+    path: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+    user: Option<String>,
+    password: Option<String>,
+    table: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct NotifySettings {
+    daily: Option<Vec<String>>,
+    alerts: Option<Vec<String>>,
+    errors: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct WebhookSettings {
+    url: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct NtfySettings {
+    url: Option<String>,
+    token: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct SmtpSettings {
+    host: Option<String>,
+    port: Option<u16>,
+    security: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    from: Option<String>,
+    to: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct MqttSettings {
+    host: Option<String>,
+    port: Option<u16>,
+    username: Option<String>,
+    password: Option<String>,
+    client_id: Option<String>,
+    topic: Option<String>,
+    discovery_prefix: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct DiscordSettings {
+    token: Option<String>,
+    channel_id: Option<u64>,
+    owners: Option<Vec<u64>>,
+    config_role: Option<u64>,
+    allow_admins: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct MatrixSettings {
+    homeserver: Option<String>,
+    access_token: Option<String>,
+    room_id: Option<String>,
+    config_users: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct TelegramSettings {
+    token: Option<String>,
+    api_url: Option<String>,
+    chats: Option<Vec<i64>>,
+    config_users: Option<Vec<i64>>,
+}
+
+/// Reads the config-file at `CONFIG_FILE` - or `powerfox.toml` if it exists - and sets each of its settings
+/// as the respective variable. Variables that are set already take precedence, so they override the file.
+pub fn load() -> Result<Option<PathBuf>> {
+    let path = match env::var("CONFIG_FILE") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) if Path::new(DEFAULT_FILE).exists() => PathBuf::from(DEFAULT_FILE),
+        Err(_) => return Ok(None),
+    };
+    let content = fs::read_to_string(&path)
+        .map_err(|err| anyhow!("Could not read config-file {}: {}", path.display(), err))?;
+    let settings: Settings = toml::from_str(&content)
+        .map_err(|err| anyhow!("Invalid config-file {}: {}", path.display(), err))?;
+
+    for (name, value) in variables(&serde_json::to_value(settings)?) {
+        if env::var_os(&name).is_none() {
+            env::set_var(name, value);
+        }
+    }
+    Ok(Some(path))
+}
+
+/// Sets each secret that isn't set from the file at its variable with the suffix `_FILE` - e.g. a Docker-secret
+/// at `DISCORD_TOKEN_FILE=/run/secrets/discord_token` - or from the credential with its name in systemd's
+/// `$CREDENTIALS_DIRECTORY`. This needs to be called before [load], so these take precedence over the config-file.
+pub fn load_secrets() -> Result<()> {
+    let credentials = env::var_os("CREDENTIALS_DIRECTORY").map(PathBuf::from);
+    for name in SECRETS {
+        if env::var_os(name).is_some() {
+            continue;
+        }
+        let path = match env::var_os(format!("{}_FILE", name)) {
+            Some(path) => PathBuf::from(path),
+            None => match &credentials {
+                Some(directory) if directory.join(name).exists() => directory.join(name),
+                _ => continue,
+            },
+        };
+        let secret = fs::read_to_string(&path)
+            .map_err(|err| anyhow!("Could not read {} from {}: {}", name, path.display(), err))?;
+        // files usually end with a newline, which isn't part of the secret
+        env::set_var(name, secret.trim_end_matches(['\r', '\n']));
+    }
+    Ok(())
+}
+
+/// The variables set in the config-file with their values.
+fn variables(settings: &Value) -> Vec<(String, String)> {
+    let mut variables = Vec::new();
+    let Value::Object(settings) = settings else {
+        return variables;
+    };
+    for (key, value) in settings {
+        match value {
+            Value::Object(section) => {
+                for (name, value) in section {
+                    if let Some(value) = to_variable(value) {
+                        variables.push((format!("{}_{}", key, name).to_uppercase(), value));
+                    }
+                }
+            }
+            value => {
+                if let Some(value) = to_variable(value) {
+                    variables.push((key.to_uppercase(), value));
+                }
+            }
+        }
+    }
+    variables
+}
+
+fn to_variable(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(value) => Some(value.clone()),
+        Value::Array(values) => Some(
+            values
+                .iter()
+                .filter_map(to_variable)
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        value => Some(value.to_string()),
+    }
+}
+
+/// Checks every setting the command uses and lists all missing or invalid ones at once,
+/// so they don't only show up when they are first used - e.g. when the daily job runs.
+pub fn validate(command: &Command) -> Result<()> {
+    let mut validation = Validation::default();
+    validation.parse::<Tz>("TIMEZONE", false);
+    validation.database();
+    match command {
+        Command::Serve | Command::Check => {
+            validation.daily();
+            validation.notifiers();
+            validation.server();
+        }
+        Command::RunDaily(args) => {
+            validation.daily();
+            if !args.dry_run {
+                validation.notifiers();
+            }
+        }
+        Command::Backfill(_) => {
+            validation.powerfox();
+            validation.weather();
+        }
+        // temperatures that aren't in the file are fetched from the weather-archive
+        Command::Import(args) if args.temperature_column.is_none() => validation.weather(),
+        Command::Devices(DevicesCommand::List { refresh: true }) => validation.powerfox(),
+        _ => {}
+    }
+
+    if validation.errors.is_empty() {
+        return Ok(());
+    }
+    let errors: Vec<String> = validation
+        .errors
+        .iter()
+        .map(|error| format!("- {}", error))
+        .collect();
+    bail!("Invalid configuration:\n{}", errors.join("\n"))
+}
+
+/// Collects the problems with the settings.
+#[derive(Default)]
+struct Validation {
+    errors: Vec<String>,
+}
+
+impl Validation {
+    fn database(&mut self) {
+        match self
+            .one_of(
+                "DATABASE_BACKEND",
+                "postgres",
+                &["postgres", "sqlite", "memory"],
+            )
+            .as_str()
+        {
+            "postgres" => {
+                self.value("DATABASE_HOST", true);
+                self.parse::<u16>("DATABASE_PORT", true);
+                self.value("DATABASE_USER", true);
+                self.value("DATABASE_PASSWORD", true);
+                self.value("DATABASE_TABLE", true);
+            }
+            "sqlite" => {
+                if cfg!(not(feature = "sqlite")) {
+                    self.error(
+                        "DATABASE_BACKEND",
+                        "'sqlite' requires building with the 'sqlite'-feature.",
+                    );
+                }
+                self.value("DATABASE_PATH", true);
+            }
+            _ => {}
+        }
+    }
+
+    /// The settings used by the daily job, besides the notifiers.
+    fn daily(&mut self) {
+        self.powerfox();
+        self.weather();
+        if let Some(threshold) = self.parse::<f64>("ANOMALY_THRESHOLD", false) {
+            if threshold <= 0.0 {
+                self.error("ANOMALY_THRESHOLD", "must be positive.");
+            }
+        }
+    }
+
+    fn powerfox(&mut self) {
+        self.parse::<Url>("POWERFOX_BASE_URL", true);
+        self.value("POWERFOX_USERNAME", true);
+        self.value("POWERFOX_PASSWORD", true);
+    }
+
+    fn weather(&mut self) {
+        self.parse::<Url>("WEATHER_BASE_URL", true);
+        self.parse::<Url>("WEATHER_ARCHIVE_URL", false);
+        for (name, limit) in [("WEATHER_LATITUDE", 90.0), ("WEATHER_LONGITUDE", 180.0)] {
+            if let Some(degrees) = self.parse::<f64>(name, true) {
+                if degrees.abs() > limit {
+                    self.error(name, &format!("must be between -{} and {}.", limit, limit));
+                }
+            }
+        }
+    }
+
+    fn notifiers(&mut self) {
+        let mut notifiers = BTreeSet::new();
+        for name in ["NOTIFY_DAILY", "NOTIFY_ALERTS", "NOTIFY_ERRORS"] {
+            notifiers.extend(self.names(
+                name,
+                DEFAULT_NOTIFIERS,
+                &["discord", "webhook", "ntfy", "email"],
+            ));
+        }
+        for notifier in notifiers {
+            match notifier.as_str() {
+                "discord" => {
+                    self.value("DISCORD_TOKEN", true);
+                    self.parse::<u64>("DISCORD_CHANNEL_ID", true);
+                }
+                "webhook" => {
+                    self.parse::<Url>("WEBHOOK_URL", true);
+                }
+                "ntfy" => {
+                    self.parse::<Url>("NTFY_URL", true);
+                }
+                _ => {
+                    self.value("SMTP_HOST", true);
+                    self.parse::<u16>("SMTP_PORT", false);
+                    self.one_of("SMTP_SECURITY", "starttls", &["starttls", "tls", "none"]);
+                    self.parse::<Mailbox>("SMTP_FROM", true);
+                    self.list::<Mailbox>("SMTP_TO", true);
+                }
+            }
+        }
+    }
+
+    /// The settings only used by `serve`.
+    fn server(&mut self) {
+        if let Some(cron) = self.value("DAILY_SCHEDULE", false) {
+            if let Err(err) = cron.parse::<Schedule>() {
+                self.error("DAILY_SCHEDULE", &format!("is no valid schedule: {}", err));
+            }
+        }
+        self.parse::<SocketAddr>("HTTP_ADDRESS", false);
+        self.parse::<i64>("HEALTH_MAX_AGE", false);
+        if self.value("MQTT_HOST", false).is_some() {
+            self.parse::<u16>("MQTT_PORT", false);
+        }
+
+        for frontend in self.names(
+            "FRONTENDS",
+            DEFAULT_FRONTENDS,
+            &["discord", "matrix", "telegram"],
+        ) {
+            match frontend.as_str() {
+                "discord" => {
+                    self.value("DISCORD_TOKEN", true);
+                    self.list::<u64>("DISCORD_OWNERS", false);
+                    self.parse::<u64>("DISCORD_CONFIG_ROLE", false);
+                    self.parse::<bool>("DISCORD_ALLOW_ADMINS", false);
+                }
+                "matrix" => {
+                    self.parse::<Url>("MATRIX_HOMESERVER", true);
+                    self.value("MATRIX_ACCESS_TOKEN", true);
+                    self.value("MATRIX_ROOM_ID", true);
+                }
+                _ => {
+                    self.value("TELEGRAM_TOKEN", true);
+                    self.parse::<Url>("TELEGRAM_API_URL", false);
+                    self.list::<i64>("TELEGRAM_CHATS", true);
+                    self.list::<i64>("TELEGRAM_CONFIG_USERS", false);
+                }
+            }
+        }
+    }
+
+    /// Adds the error, unless it was added already - e.g. for `DISCORD_TOKEN`, which is used twice.
+    fn error(&mut self, name: &str, problem: &str) {
+        let error = format!("{} ({}) {}", name, key(name), problem);
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    fn value(&mut self, name: &str, required: bool) -> Option<String> {
+        match env::var(name) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                if required {
+                    self.error(name, "is not set.");
+                }
+                None
+            }
+        }
+    }
+
+    fn parse<T>(&mut self, name: &str, required: bool) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.value(name, required)?;
+        match value.trim().parse() {
+            Ok(parsed) => Some(parsed),
+            Err(err) => {
+                self.error(name, &format!("is invalid: {}", err));
+                None
+            }
+        }
+    }
+
+    /// Checks each of the comma-separated values.
+    fn list<T>(&mut self, name: &str, required: bool)
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let Some(values) = self.value(name, required) else {
+            return;
+        };
+        for value in values
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+        {
+            if let Err(err) = value.parse::<T>() {
+                self.error(
+                    name,
+                    &format!("contains invalid value '{}': {}", value, err),
+                );
+            }
+        }
+    }
+
+    /// The value, which must be one of the options.
+    fn one_of(&mut self, name: &str, default: &str, options: &[&str]) -> String {
+        let value = self.value(name, false).unwrap_or(default.to_string());
+        if !options.contains(&value.as_str()) {
+            self.error(
+                name,
+                &format!("is '{}', but must be one of {}.", value, options.join(", ")),
+            );
+        }
+        value
+    }
+
+    /// The known ones of the comma-separated names.
+    fn names(&mut self, name: &str, default: &str, options: &[&str]) -> Vec<String> {
+        let names = self.value(name, false).unwrap_or(default.to_string());
+        let mut known = Vec::new();
+        for value in names
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+        {
+            if options.contains(&value) {
+                known.push(value.to_string());
+            } else {
+                self.error(
+                    name,
+                    &format!("contains unknown '{}', use {}.", value, options.join(", ")),
+                );
+            }
+        }
+        known
+    }
+}
+
+/// The key of the variable in the config-file, e.g. `database.host` for `DATABASE_HOST`.
+fn key(name: &str) -> String {
+    let name = name.to_lowercase();
+    for section in SECTIONS {
+        if let Some(key) = name.strip_prefix(&format!("{}_", section)) {
+            return format!("{}.{}", section, key);
+        }
+    }
+    name
+}
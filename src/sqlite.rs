@@ -0,0 +1,529 @@
+use crate::db::{
+    Aggregate, Config, ConfigHistory, CreateDay, Day, Days, Device, Granularity, Hour, JobRun,
+    Peak, Storage, Trigger,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use log::info;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    SqliteConnection, SqlitePool,
+};
+use std::env;
+
+/// [Storage] backed by a single SQLite-file, which is lighter than running PostgreSQL for a single household.
+///
+/// NOTE the query-macros only support one database at a time, so queries are checked at runtime here.
+#[derive(Clone)]
+pub struct Sqlite {
+    pool: SqlitePool,
+}
+
+impl Sqlite {
+    /// Opens the database at `DATABASE_PATH`, creating and migrating it if necessary unless it's opened read-only.
+    pub async fn new(read_only: bool) -> Result<Self> {
+        let path = env::var("DATABASE_PATH")?;
+
+        let mut options = SqliteConnectOptions::new()
+            .filename(&path)
+            .read_only(read_only);
+        // switching the journal-mode writes to the file
+        if !read_only {
+            options = options
+                .create_if_missing(true)
+                .journal_mode(SqliteJournalMode::Wal);
+        }
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await?;
+
+        // migrations are embedded at compile-time and only applied once
+        if !read_only {
+            sqlx::migrate!("migrations/sqlite").run(&pool).await?;
+        }
+
+        info!("Set up SQLite-database at {}.", path);
+        Ok(Sqlite { pool })
+    }
+}
+
+/// Saves the day within the transaction, replacing an existing day with the same date.
+///
+/// NOTE writes don't use RETURNING: fetch_one doesn't step the statement to its end,
+/// so SQLite wouldn't commit the write - the saved values are the given ones anyway.
+async fn insert_day(connection: &mut SqliteConnection, day: CreateDay) -> Result<Day> {
+    sqlx::query(
+        "INSERT INTO days (heating_consumption, general_consumption, average_temperature, date) VALUES (?, ?, ?, ?)
+        ON CONFLICT (date) DO UPDATE SET heating_consumption = excluded.heating_consumption, general_consumption = excluded.general_consumption, average_temperature = excluded.average_temperature",
+    )
+    .bind(day.heating_consumption)
+    .bind(day.general_consumption)
+    .bind(day.average_temperature)
+    .bind(day.date)
+    .execute(connection)
+    .await?;
+    Ok(day.into())
+}
+
+#[async_trait]
+impl Storage for Sqlite {
+    async fn save_days(&self, days: Vec<CreateDay>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for day in days {
+            insert_day(&mut tx, day).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn save_import(&self, day: CreateDay, hours: Vec<Hour>, peak: Peak) -> Result<Day> {
+        let mut tx = self.pool.begin().await?;
+        let day = insert_day(&mut tx, day).await?;
+        for hour in hours {
+            sqlx::query(
+                "INSERT INTO hours (timestamp, date, heating_consumption, general_consumption) VALUES (?, ?, ?, ?)
+                ON CONFLICT (timestamp) DO UPDATE SET date = excluded.date, heating_consumption = excluded.heating_consumption, general_consumption = excluded.general_consumption",
+            )
+            .bind(hour.timestamp)
+            .bind(hour.date)
+            .bind(hour.heating_consumption)
+            .bind(hour.general_consumption)
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query(
+            "INSERT INTO peaks (date, heating_max, general_max) VALUES (?, ?, ?)
+            ON CONFLICT (date) DO UPDATE SET heating_max = excluded.heating_max, general_max = excluded.general_max",
+        )
+        .bind(peak.date)
+        .bind(peak.heating_max)
+        .bind(peak.general_max)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(day)
+    }
+
+    async fn get_day(&self, date: NaiveDate) -> Result<Option<Day>> {
+        let day = sqlx::query_as::<_, Day>("SELECT heating_consumption, general_consumption, average_temperature, date FROM days WHERE date = ?")
+            .bind(date)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(day)
+    }
+
+    async fn get_latest_day(&self) -> Result<Option<Day>> {
+        let day = sqlx::query_as::<_, Day>("SELECT heating_consumption, general_consumption, average_temperature, date FROM days ORDER BY date DESC LIMIT 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(day)
+    }
+
+    async fn get_days_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Days> {
+        let days = sqlx::query_as::<_, Day>("SELECT heating_consumption, general_consumption, average_temperature, date FROM days WHERE date BETWEEN ? AND ? ORDER BY date")
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(Days(days))
+    }
+
+    async fn get_hours_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Hour>> {
+        let hours = sqlx::query_as::<_, Hour>("SELECT timestamp, date, heating_consumption, general_consumption FROM hours WHERE date BETWEEN ? AND ? ORDER BY timestamp")
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(hours)
+    }
+
+    async fn get_peaks_between(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<Peak>> {
+        let peaks = sqlx::query_as::<_, Peak>("SELECT date, heating_max, general_max FROM peaks WHERE date BETWEEN ? AND ? ORDER BY date")
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(peaks)
+    }
+
+    async fn aggregate(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        granularity: Granularity,
+    ) -> Result<Vec<Aggregate>> {
+        // joins the config valid on each day, falling back to the first config like ConfigHistory::at
+        let aggregates = sqlx::query_as::<_, Aggregate>(
+            "SELECT CASE ?3
+                    WHEN 'day' THEN d.date
+                    WHEN 'week' THEN date(d.date, 'weekday 0', '-6 days')
+                    ELSE strftime('%Y-%m-01', d.date)
+                END AS period,
+                count(*) AS days,
+                sum(d.heating_consumption) AS heating_sum,
+                avg(d.heating_consumption) AS heating_avg,
+                min(d.heating_consumption) AS heating_min,
+                max(d.heating_consumption) AS heating_max,
+                sum(d.general_consumption) AS general_sum,
+                avg(d.general_consumption) AS general_avg,
+                min(d.general_consumption) AS general_min,
+                max(d.general_consumption) AS general_max,
+                avg(d.average_temperature) AS average_temperature,
+                sum(d.heating_consumption * c.cost_heating + c.base_fee_heating / CAST(strftime('%d', d.date, 'start of month', '+1 month', '-1 day') AS REAL)) AS heating_cost,
+                sum(d.general_consumption * c.cost_general + c.base_fee_general / CAST(strftime('%d', d.date, 'start of month', '+1 month', '-1 day') AS REAL)) AS general_cost
+            FROM days d
+            JOIN config c ON c.valid_from = COALESCE(
+                (SELECT max(valid_from) FROM config WHERE valid_from <= d.date),
+                (SELECT min(valid_from) FROM config))
+            WHERE d.date BETWEEN ?1 AND ?2
+            GROUP BY period
+            ORDER BY period",
+        )
+        .bind(from)
+        .bind(to)
+        .bind(granularity.as_str())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(aggregates)
+    }
+
+    async fn get_config_history(&self) -> Result<ConfigHistory> {
+        let configs = sqlx::query_as::<_, Config>("SELECT * FROM config ORDER BY valid_from")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(ConfigHistory(configs))
+    }
+
+    async fn save_config(&self, config: Config) -> Result<Config> {
+        // see insert_day for why this doesn't use RETURNING
+        sqlx::query(
+            "INSERT INTO config (valid_from, cost_heating, cost_general, monthly_budget_heating, monthly_budget_general, base_fee_heating, base_fee_general) VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (valid_from) DO UPDATE SET cost_heating = excluded.cost_heating, cost_general = excluded.cost_general, monthly_budget_heating = excluded.monthly_budget_heating, monthly_budget_general = excluded.monthly_budget_general, base_fee_heating = excluded.base_fee_heating, base_fee_general = excluded.base_fee_general",
+        )
+        .bind(config.valid_from)
+        .bind(config.cost_heating)
+        .bind(config.cost_general)
+        .bind(config.monthly_budget_heating)
+        .bind(config.monthly_budget_general)
+        .bind(config.base_fee_heating)
+        .bind(config.base_fee_general)
+        .execute(&self.pool)
+        .await?;
+        Ok(config)
+    }
+
+    async fn get_schedule(&self, name: &str) -> Result<Option<String>> {
+        let cron = sqlx::query_scalar::<_, String>("SELECT cron FROM schedules WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(cron)
+    }
+
+    async fn save_schedule(&self, name: &str, cron: &str) -> Result<()> {
+        sqlx::query("INSERT INTO schedules (name, cron) VALUES (?, ?) ON CONFLICT (name) DO UPDATE SET cron = excluded.cron")
+            .bind(name)
+            .bind(cron)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_devices(&self) -> Result<Vec<Device>> {
+        let devices = sqlx::query_as::<_, Device>("SELECT * FROM devices ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(devices)
+    }
+
+    async fn save_devices(&self, devices: Vec<Device>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for device in devices {
+            sqlx::query(
+                "INSERT INTO devices (device_id, name, main_device) VALUES (?, ?, ?)
+                ON CONFLICT (device_id) DO UPDATE SET name = excluded.name, main_device = excluded.main_device",
+            )
+            .bind(device.device_id)
+            .bind(device.name)
+            .bind(device.main_device)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn start_job_run(&self, job: &str, trigger: Trigger) -> Result<i64> {
+        // see insert_day for why this doesn't use RETURNING
+        let result = sqlx::query(
+            "INSERT INTO job_runs (job, triggered_by, started_at, status) VALUES (?, ?, ?, 'running')",
+        )
+        .bind(job)
+        .bind(trigger.as_str())
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    async fn finish_job_run(
+        &self,
+        id: i64,
+        error: Option<String>,
+        days: Vec<NaiveDate>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE job_runs SET finished_at = ?, status = ?, error = ?, days = ? WHERE id = ?",
+        )
+        .bind(Utc::now())
+        .bind(JobRun::status_of(&error))
+        .bind(error)
+        .bind(JobRun::join_days(&days))
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_job_runs(&self, limit: i64) -> Result<Vec<JobRun>> {
+        let runs = sqlx::query_as::<_, JobRun>(
+            "SELECT * FROM job_runs ORDER BY started_at DESC, id DESC LIMIT ?",
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(runs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    /// A migrated in-memory database, which only lives as long as its single connection.
+    async fn memory() -> Sqlite {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("migrations/sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        Sqlite { pool }
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    fn day(date: NaiveDate, heating_consumption: f64, general_consumption: f64) -> CreateDay {
+        CreateDay {
+            heating_consumption,
+            general_consumption,
+            average_temperature: 10.0,
+            date,
+        }
+    }
+
+    #[tokio::test]
+    async fn saved_days_are_read_back() {
+        let db = memory().await;
+        assert!(db.get_latest_day().await.unwrap().is_none());
+
+        db.save_days(vec![day(date(3, 1), 10.0, 3.0), day(date(3, 2), 12.0, 4.0)])
+            .await
+            .unwrap();
+        let saved = db.get_day(date(3, 2)).await.unwrap().unwrap();
+        assert_eq!(
+            (saved.heating_consumption, saved.general_consumption),
+            (12.0, 4.0)
+        );
+        assert!(db.get_day(date(3, 3)).await.unwrap().is_none());
+
+        // saving a day again replaces it
+        let replaced = db
+            .save_import(
+                day(date(3, 2), 20.0, 5.0),
+                vec![Hour {
+                    timestamp: Utc.with_ymd_and_hms(2026, 3, 2, 12, 0, 0).unwrap(),
+                    date: date(3, 2),
+                    heating_consumption: 1.0,
+                    general_consumption: 0.5,
+                }],
+                Peak {
+                    date: date(3, 2),
+                    heating_max: 3.0,
+                    general_max: 1.5,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(replaced.heating_consumption, 20.0);
+        let days = db
+            .get_days_between(date(3, 1), date(3, 31))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(
+            days.iter()
+                .map(|day| (day.date, day.heating_consumption))
+                .collect::<Vec<_>>(),
+            vec![(date(3, 1), 10.0), (date(3, 2), 20.0)]
+        );
+        assert_eq!(db.get_latest_day().await.unwrap().unwrap().date, date(3, 2));
+        assert_eq!(
+            db.get_hours_between(date(3, 2), date(3, 2))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            db.get_peaks_between(date(3, 1), date(3, 2)).await.unwrap()[0].heating_max,
+            3.0
+        );
+    }
+
+    #[tokio::test]
+    async fn days_are_saved_all_or_none() {
+        let db = memory().await;
+        // SQLite stores NaN as NULL, which the column doesn't allow
+        let result = db
+            .save_days(vec![
+                day(date(3, 1), 10.0, 3.0),
+                day(date(3, 2), f64::NAN, 4.0),
+            ])
+            .await;
+        assert!(result.is_err());
+        assert!(db.get_day(date(3, 1)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn config_history_is_ordered_by_validity() {
+        let db = memory().await;
+        let default = db.get_config().await.unwrap();
+        assert_eq!(
+            default.valid_from,
+            NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+        );
+        assert_eq!(default.cost_heating, 0.23);
+
+        db.save_config(
+            default
+                .clone()
+                .with_valid_from(date(7, 1))
+                .with_cost_heating(0.3),
+        )
+        .await
+        .unwrap();
+        db.save_config(
+            default
+                .clone()
+                .with_valid_from(date(3, 1))
+                .with_cost_heating(0.25),
+        )
+        .await
+        .unwrap();
+        // the same date replaces the version
+        db.save_config(default.with_valid_from(date(3, 1)).with_cost_heating(0.26))
+            .await
+            .unwrap();
+
+        let history = db.get_config_history().await.unwrap();
+        assert_eq!(
+            history
+                .0
+                .iter()
+                .map(|config| (config.valid_from, config.cost_heating))
+                .collect::<Vec<_>>(),
+            vec![
+                (NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(), 0.23),
+                (date(3, 1), 0.26),
+                (date(7, 1), 0.3)
+            ]
+        );
+        assert_eq!(
+            db.get_config_at(date(6, 30)).await.unwrap().cost_heating,
+            0.26
+        );
+    }
+
+    #[tokio::test]
+    async fn job_runs_are_recorded() {
+        let db = memory().await;
+        let failed = db.start_job_run("daily", Trigger::Schedule).await.unwrap();
+        db.finish_job_run(failed, Some("Powerfox is down".to_string()), Vec::new())
+            .await
+            .unwrap();
+        let succeeded = db.start_job_run("backfill", Trigger::Cli).await.unwrap();
+        db.finish_job_run(succeeded, None, vec![date(3, 1), date(3, 2)])
+            .await
+            .unwrap();
+        let running = db.start_job_run("daily", Trigger::Http).await.unwrap();
+
+        let runs = db.get_job_runs(10).await.unwrap();
+        assert_eq!(
+            runs.iter().map(|run| run.id).collect::<Vec<_>>(),
+            vec![running, succeeded, failed]
+        );
+        assert_eq!(runs[0].status, "running");
+        assert!(runs[0].finished_at.is_none());
+        assert_eq!(runs[0].triggered_by, Trigger::Http.as_str());
+        assert_eq!(runs[1].status, "success");
+        assert_eq!(runs[1].days, "2026-03-01,2026-03-02");
+        assert_eq!(runs[2].status, "error");
+        assert_eq!(runs[2].error.as_deref(), Some("Powerfox is down"));
+        assert!(runs[2].finished_at.unwrap() >= runs[2].started_at);
+        assert_eq!(db.get_job_runs(1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn weeks_are_aggregated_with_the_config_of_each_day() {
+        let db = memory().await;
+        let default = db.get_config().await.unwrap();
+        // from Wednesday on, general costs more and has a base fee of 31€ per month, i.e. 1€ per day in March
+        db.save_config(Config {
+            cost_general: 0.5,
+            base_fee_general: 31.0,
+            ..default.with_valid_from(date(3, 4))
+        })
+        .await
+        .unwrap();
+        // Sunday the 1st, then Monday to Wednesday of the next week
+        db.save_days(
+            [1, 2, 3, 4]
+                .into_iter()
+                .map(|offset| day(date(3, 1) + Duration::days(offset - 1), offset as f64, 2.0))
+                .collect(),
+        )
+        .await
+        .unwrap();
+
+        let weeks = db
+            .aggregate(date(3, 1), date(3, 31), Granularity::Week)
+            .await
+            .unwrap();
+        assert_eq!(
+            weeks
+                .iter()
+                .map(|week| (week.period, week.days))
+                .collect::<Vec<_>>(),
+            vec![(date(2, 23), 1), (date(3, 2), 3)]
+        );
+        let week = &weeks[1];
+        assert_eq!(week.heating_sum, 2.0 + 3.0 + 4.0);
+        assert_eq!(week.heating_avg, 3.0);
+        assert_eq!((week.heating_min, week.heating_max), (2.0, 4.0));
+        assert_eq!(week.general_sum, 6.0);
+        assert!((week.heating_cost - 9.0 * 0.23).abs() < 1e-9);
+        assert!((week.general_cost - (2.0 * 0.3 * 2.0 + 2.0 * 0.5 + 1.0)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    commands::{self, Answer},
+    db::Db,
+    markup,
+    scheduler::Scheduler,
+};
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use reqwest::{
+    multipart::{Form, Part},
+    Client, RequestBuilder,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+use std::{collections::HashSet, env, time::Duration};
+
+/// Used if `TELEGRAM_API_URL` isn't set.
+const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+/// How long a request for updates waits for new messages, in seconds.
+const POLL_TIMEOUT: u64 = 30;
+
+/// Answers text-commands via the [Bot API](https://core.telegram.org/bots/api), polling for new messages.
+#[derive(Clone)]
+struct Telegram {
+    client: Client,
+    /// The API-URL including the token.
+    url: String,
+    /// The chats from `TELEGRAM_CHATS`, the only ones that are answered.
+    chats: HashSet<i64>,
+    /// The users from `TELEGRAM_CONFIG_USERS`, who may change the config.
+    config_users: HashSet<i64>,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    chat: Chat,
+    from: Option<User>,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+#[derive(Deserialize)]
+struct User {
+    id: i64,
+    first_name: String,
+}
+
+/// Answers commands until the app is stopped.
+pub async fn start_bot(db: Db, scheduler: Scheduler) -> Result<()> {
+    let telegram = Telegram::from_env()?;
+    let mut offset = 0;
+    info!("Polling Telegram for commands.");
+    loop {
+        let updates = match telegram.get_updates(offset).await {
+            Ok(updates) => updates,
+            Err(err) => {
+                warn!("Could not get updates from Telegram: {}", err);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+
+        for update in updates {
+            offset = update.update_id + 1;
+            let Some(message) = update.message else {
+                continue;
+            };
+            // commands can take a while, so they shouldn't block the following ones
+            let (telegram, db, scheduler) = (telegram.clone(), db.clone(), scheduler.clone());
+            tokio::spawn(async move { telegram.answer(&db, &scheduler, message).await });
+        }
+    }
+}
+
+/// Checks the token, returning the bot's name.
+pub async fn check() -> Result<String> {
+    let me: serde_json::Value = Telegram::from_env()?.call("getMe", json!({})).await?;
+    Ok(format!(
+        "logged in as {}",
+        me["username"].as_str().unwrap_or_default()
+    ))
+}
+
+impl Telegram {
+    fn from_env() -> Result<Self> {
+        let token =
+            env::var("TELEGRAM_TOKEN").map_err(|_| anyhow!("TELEGRAM_TOKEN is not set."))?;
+        let api_url = env::var("TELEGRAM_API_URL").unwrap_or(DEFAULT_API_URL.to_string());
+        // anyone can message the bot, so it only answers known chats
+        let chats = parse_ids(
+            &env::var("TELEGRAM_CHATS").map_err(|_| anyhow!("TELEGRAM_CHATS is not set."))?,
+        )?;
+        let config_users = match env::var("TELEGRAM_CONFIG_USERS") {
+            Ok(ids) => parse_ids(&ids)?,
+            Err(_) => HashSet::new(),
+        };
+
+        Ok(Telegram {
+            client: Client::new(),
+            url: format!("{}/bot{}", api_url, token),
+            chats,
+            config_users,
+        })
+    }
+
+    async fn answer(&self, db: &Db, scheduler: &Scheduler, message: Message) {
+        let (Some(text), Some(from)) = (message.text, message.from) else {
+            return;
+        };
+        if !self.chats.contains(&message.chat.id) {
+            return;
+        }
+
+        let author = format!("{} ({})", from.first_name, from.id);
+        let may_configure = self.config_users.contains(&from.id);
+        let Some(answer) = commands::run_text(db, scheduler, &text, &author, may_configure).await
+        else {
+            return;
+        };
+        if let Err(err) = self.send_answer(message.chat.id, answer).await {
+            warn!(
+                "Could not answer in Telegram-chat {}: {}",
+                message.chat.id, err
+            );
+        }
+    }
+
+    async fn get_updates(&self, offset: i64) -> Result<Vec<Update>> {
+        self.call(
+            "getUpdates",
+            json!({
+                "offset": offset,
+                "timeout": POLL_TIMEOUT,
+                "allowed_updates": ["message"],
+            }),
+        )
+        .await
+    }
+
+    /// Sends the answer as HTML, with its file as a document captioned with the text.
+    async fn send_answer(&self, chat_id: i64, answer: Answer) -> Result<()> {
+        let text = markup::to_html(&answer.text, "\n");
+        let _: serde_json::Value = match answer.file {
+            None => {
+                self.call(
+                    "sendMessage",
+                    json!({ "chat_id": chat_id, "text": text, "parse_mode": "HTML" }),
+                )
+                .await?
+            }
+            Some(file) => {
+                let form = Form::new()
+                    .text("chat_id", chat_id.to_string())
+                    .text("caption", text)
+                    .text("parse_mode", "HTML")
+                    .part("document", Part::bytes(file.data).file_name(file.name));
+                self.send("sendDocument", self.request("sendDocument").multipart(form))
+                    .await?
+            }
+        };
+        Ok(())
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, body: serde_json::Value) -> Result<T> {
+        self.send(method, self.request(method).json(&body)).await
+    }
+
+    fn request(&self, method: &str) -> RequestBuilder {
+        self.client.post(format!("{}/{}", self.url, method))
+    }
+
+    async fn send<T: DeserializeOwned>(&self, method: &str, request: RequestBuilder) -> Result<T> {
+        // errors contain the URL, which includes the token, and end up in the logs
+        let response: Response<T> = request
+            .timeout(Duration::from_secs(POLL_TIMEOUT + 10))
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)?
+            .json()
+            .await
+            .map_err(reqwest::Error::without_url)?;
+        match (response.ok, response.result) {
+            (true, Some(result)) => Ok(result),
+            _ => Err(anyhow!(
+                "Telegram answered {} with: {}",
+                method,
+                response.description.unwrap_or_default()
+            )),
+        }
+    }
+}
+
+fn parse_ids(ids: &str) -> Result<HashSet<i64>> {
+    let mut parsed = HashSet::new();
+    for id in ids.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+        parsed.insert(id.parse()?);
+    }
+    Ok(parsed)
+}
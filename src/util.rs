@@ -1,5 +1,78 @@
-use chrono::NaiveDateTime;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
 use serde::Deserialize;
+use std::{env, sync::OnceLock};
+
+static TIMEZONE: OnceLock<Tz> = OnceLock::new();
+
+/// Reads the timezone from `TIMEZONE` (e.g. `Europe/Berlin`), falling back to UTC if it is not set.
+/// This needs to be called once at startup, before any dates are calculated.
+pub fn init_timezone() -> Result<Tz> {
+    let timezone = match env::var("TIMEZONE") {
+        Ok(name) => name
+            .parse::<Tz>()
+            .map_err(|err| anyhow!("Could not parse TIMEZONE '{}': {}", name, err))?,
+        Err(_) => Tz::UTC,
+    };
+    Ok(*TIMEZONE.get_or_init(|| timezone))
+}
+
+/// The configured timezone used for all date-calculations and scheduled jobs.
+pub fn timezone() -> Tz {
+    *TIMEZONE.get().unwrap_or(&Tz::UTC)
+}
+
+/// The current time in the configured timezone.
+pub fn now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&timezone())
+}
+
+/// The current date in the configured timezone.
+pub fn today() -> NaiveDate {
+    now().date_naive()
+}
+
+/// Yesterday's date in the configured timezone.
+pub fn yesterday() -> NaiveDate {
+    today() - Duration::days(1)
+}
+
+/// The start of the given date in the configured timezone.
+pub fn start_of_day(date: NaiveDate) -> DateTime<Tz> {
+    start_of_day_in(date, timezone())
+}
+
+/// The start of the given date in the timezone.
+pub fn start_of_day_in(date: NaiveDate, timezone: Tz) -> DateTime<Tz> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    timezone
+        .from_local_datetime(&midnight)
+        .earliest()
+        // midnight may not exist when DST starts, so fall back to interpreting it as UTC
+        .unwrap_or_else(|| timezone.from_utc_datetime(&midnight))
+}
+
+/// The first day of the month of the given date.
+pub fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// The first day of the year of the given date.
+pub fn first_of_year(date: NaiveDate) -> NaiveDate {
+    date.with_ordinal(1).unwrap_or(date)
+}
+
+/// The number of days in the month of the given date.
+pub fn days_in_month(date: NaiveDate) -> u32 {
+    let (year, month) = match date.month() {
+        12 => (date.year() + 1, 1),
+        month => (date.year(), month + 1),
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .map(|first_of_next_month| first_of_next_month.pred_opt().map_or(31, |last| last.day()))
+        .unwrap_or(31)
+}
 
 pub fn deserialize_datetime<'de, D>(deserializer: D) -> Result<Vec<NaiveDateTime>, D::Error>
 where